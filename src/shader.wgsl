@@ -14,35 +14,73 @@ struct TextureTransform {
     bitmap_transform: mat4x4<f32>,
     inverse_bitmap_transform: mat4x4<f32>,
     image_dimension: vec4<f32>,
+    // x: brightness, y: contrast, z: gamma, w: unused.
+    color_adjust: vec4<f32>,
+    // Tile origin shift in multiples of the image size.
+    pattern_offset: vec2<f32>,
+    // Pixel size of the render target, y down.
+    surface_size: vec2<f32>,
 };
 
 @group(0) @binding(2) var<uniform> u_texture: TextureTransform;
 
+// Tinted variant, used by the colored pipeline.
+struct ColoredVertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+struct ColoredVertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
 @vertex
 fn vs_main(
     model: VertexInput,
 ) -> VertexOutput {
-    var out: VertexOutput;
+    return pattern_vertex(model.position, model.uv);
+}
 
-    let container_dimension = vec2<f32>(
-        640.0 * u_texture.texture_matrix[0][0],
-        360.0 * u_texture.texture_matrix[1][1],
-    );
+@vertex
+fn vs_main_colored(
+    model: ColoredVertexInput,
+) -> ColoredVertexOutput {
+    let pattern = pattern_vertex(model.position, model.uv);
+    var out: ColoredVertexOutput;
+    out.clip_position = pattern.clip_position;
+    out.tex_coords = pattern.tex_coords;
+    out.color = model.color;
+    return out;
+}
 
-    let bitmap_transform = u_texture.texture_matrix * u_texture.inverse_bitmap_transform;
+// Instanced tiling: each instance shifts the quad by its tile offset.
+@vertex
+fn vs_main_instanced(
+    model: VertexInput,
+    @location(3) tile_offset: vec2<f32>,
+) -> VertexOutput {
+    return pattern_vertex(model.position + vec3<f32>(tile_offset, 0.0), model.uv);
+}
+
+fn pattern_vertex(position: vec3<f32>, uv: vec2<f32>) -> VertexOutput {
+    var out: VertexOutput;
 
-    var temp = u_texture.texture_matrix * vec4<f32>(model.position.xy, 0.0, 1.0);
+    var temp = u_texture.texture_matrix * vec4<f32>(position.xy, 0.0, 1.0);
     let pos = vec3<f32>(
-        temp.x / 640.0 * 2.0 - 1.0,
-        temp.y / 360.0 * -2.0 + 1.0,
+        temp.x / u_texture.surface_size.x * 2.0 - 1.0,
+        temp.y / u_texture.surface_size.y * -2.0 + 1.0,
         1.0,
     );
 
-    let new_image_dimension = u_texture.bitmap_transform * u_texture.image_dimension;
-    let repeat_x = abs(container_dimension.x / new_image_dimension.x);
-    let repeat_y = abs(container_dimension.y / new_image_dimension.y);
+    // Map the quad's uv into image pixels, undo the bitmap transform there
+    // and normalise back so the sampler's address mode does the repeating.
+    let image_position = vec4<f32>(uv * u_texture.image_dimension.xy, 0.0, 1.0);
+    let pattern_position = u_texture.inverse_bitmap_transform * image_position;
 
-    out.tex_coords = (model.position.xy / new_image_dimension.xy).yx;
+    out.tex_coords = pattern_position.xy / u_texture.image_dimension.xy - u_texture.pattern_offset;
     out.clip_position = vec4<f32>(pos, 1.0);
 
     return out;
@@ -54,7 +92,18 @@ var t_diffuse: texture_2d<f32>;
 @group(0)@binding(1)
 var s_diffuse: sampler;
 
+fn adjust_color(color: vec4<f32>) -> vec4<f32> {
+    let adjust = u_texture.color_adjust;
+    let rgb = max((color.rgb * adjust.x - 0.5) * adjust.y + 0.5, vec3<f32>(0.0));
+    return vec4<f32>(pow(rgb, vec3<f32>(1.0 / adjust.z)), color.a);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
-}
\ No newline at end of file
+    return adjust_color(textureSample(t_diffuse, s_diffuse, in.tex_coords));
+}
+
+@fragment
+fn fs_main_colored(in: ColoredVertexOutput) -> @location(0) vec4<f32> {
+    return adjust_color(textureSample(t_diffuse, s_diffuse, in.tex_coords)) * in.color;
+}
@@ -1,32 +1,521 @@
-use std::num::NonZeroU32;
+use std::borrow::Cow;
+use std::fmt;
+use std::io::Cursor;
+use std::num::{NonZeroU32, NonZeroU8};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::*;
-use image::GenericImageView;
 use wgpu::util::DeviceExt;
 
+pub type Result<T, E = TextureError> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Decode(image::ImageError),
+    TooLarge {
+        requested: u32,
+        max: u32,
+    },
+    NonInvertibleTransform,
+    DimensionMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    InvalidLength {
+        expected: usize,
+        actual: usize,
+    },
+    Empty {
+        width: u32,
+        height: u32,
+        layers: u32,
+    },
+    MissingFeatures(wgpu::Features),
+    UnsupportedFormat(wgpu::TextureFormat),
+    Map(wgpu::BufferAsyncError),
+    /// A region that doesn't fit inside the texture.
+    OutOfBounds {
+        origin: (u32, u32),
+        size: (u32, u32),
+        bounds: (u32, u32),
+    },
+    /// A `TextureBuilder` setting with a value wgpu would reject.
+    InvalidOption {
+        option: &'static str,
+        reason: String,
+    },
+    /// The texture lacks a usage the operation needs.
+    MissingUsage(wgpu::TextureUsages),
+    /// CPU uploads and readback don't work on multisampled textures.
+    Multisampled,
+    Invalid(String),
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::Io { path, .. } => write!(f, "failed to read {}", path.display()),
+            TextureError::Decode(_) => write!(f, "failed to decode image"),
+            TextureError::TooLarge { requested, max } => write!(
+                f,
+                "requested size of {} exceeds the device limit of {}",
+                requested, max
+            ),
+            TextureError::NonInvertibleTransform => {
+                write!(f, "bitmap transform matrix is not invertible")
+            }
+            TextureError::DimensionMismatch { expected, actual } => write!(
+                f,
+                "expected {}x{} pixels, got {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            TextureError::InvalidLength { expected, actual } => {
+                write!(
+                    f,
+                    "expected {} bytes of pixel data, got {}",
+                    expected, actual
+                )
+            }
+            TextureError::Empty { layers: 0, .. } => {
+                write!(f, "cannot create a texture array from zero images")
+            }
+            TextureError::Empty { width, height, .. } => write!(
+                f,
+                "image is {}x{}, textures must not be empty",
+                width, height
+            ),
+            TextureError::MissingFeatures(features) => {
+                write!(f, "device is missing the {:?} feature", features)
+            }
+            TextureError::UnsupportedFormat(format) => {
+                write!(f, "operation is not supported on {:?} textures", format)
+            }
+            TextureError::Map(_) => write!(f, "failed to map readback buffer"),
+            TextureError::OutOfBounds {
+                origin,
+                size,
+                bounds,
+            } => write!(
+                f,
+                "{}x{} region at ({}, {}) does not fit in a {}x{} texture",
+                size.0, size.1, origin.0, origin.1, bounds.0, bounds.1
+            ),
+            TextureError::InvalidOption { option, reason } => {
+                write!(f, "invalid {}: {}", option, reason)
+            }
+            TextureError::MissingUsage(usage) => {
+                write!(f, "texture was not created with {:?} usage", usage)
+            }
+            TextureError::Multisampled => {
+                write!(f, "multisampled textures cannot be written or read back")
+            }
+            TextureError::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TextureError::Io { source, .. } => Some(source),
+            TextureError::Decode(e) => Some(e),
+            TextureError::Map(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for TextureError {
+    fn from(e: image::ImageError) -> Self {
+        TextureError::Decode(e)
+    }
+}
+
+/// The texture, view, sampler and quad are reference counted so `share` can
+/// hand out copies that only own a uniform buffer of their own.
 pub struct Texture {
-    pub texture: wgpu::Texture,
-    pub view: wgpu::TextureView,
-    pub sampler: wgpu::Sampler,
-    pub vertex_buffer: wgpu::Buffer,
-    pub index_buffer: wgpu::Buffer,
-    pub uniform_buffer: wgpu::Buffer,
+    pub texture: Arc<wgpu::Texture>,
+    pub view: Arc<wgpu::TextureView>,
+    pub sampler: Arc<wgpu::Sampler>,
+    pub vertex_buffer: Option<Arc<wgpu::Buffer>>,
+    pub index_buffer: Option<Arc<wgpu::Buffer>>,
+    pub instance_buffer: Option<Arc<wgpu::Buffer>>,
+    pub uniform_buffer: Option<wgpu::Buffer>,
+    view_dimension: wgpu::TextureViewDimension,
+    sampler_desc: wgpu::SamplerDescriptor<'static>,
+    transform_stride: Option<wgpu::BufferAddress>,
+    quad: [Vertex; 4],
+    content_size: (u32, u32),
+    has_alpha: bool,
+    load_scale: f32,
+    /// What the texture was built with, so `reload` can rebuild it the same
+    /// way. The label is owned here since the builder only borrows it.
+    builder: TextureBuilder<'static>,
+    label: Option<String>,
+    drop_log: DropLog,
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vertex {
     pub position: [f32; 3],
     pub tex_coords: [f32; 2],
 }
 
+/// A `Vertex` with a tint the colored pipeline multiplies the pattern by.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VertexColored {
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub color: [f32; 4],
+}
+
+/// Per-instance data for instanced tiling, in the quad's pixel space.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileInstance {
+    pub offset: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TextureTransform {
     pub transform: [[f32; 4]; 4],
     pub bitmap_transform: [[f32; 4]; 4],
     pub inverse_bitmap_transform: [[f32; 4]; 4],
     pub image_dimension: [f32; 4],
+    /// Brightness, contrast and gamma applied to the sampled colour, the last
+    /// component is unused. See `COLOR_ADJUST_IDENTITY`.
+    pub color_adjust: [f32; 4],
+    /// Shifts the tile origin, in multiples of the image size, for scrolling
+    /// a repeating pattern without touching the matrices.
+    pub pattern_offset: [f32; 2],
+    /// Size in pixels of the target the quad is drawn into; `transform` maps
+    /// into this space and the shader divides by it to reach clip space.
+    pub surface_size: [f32; 2],
+}
+
+// Must match `TextureTransform` in shader.wgsl: three mat4x4s, two vec4s
+// and two vec2s, with no padding in between.
+const _: () = {
+    use std::mem::{offset_of, size_of};
+    assert!(size_of::<TextureTransform>() == 240);
+    assert!(offset_of!(TextureTransform, bitmap_transform) == 64);
+    assert!(offset_of!(TextureTransform, inverse_bitmap_transform) == 128);
+    assert!(offset_of!(TextureTransform, image_dimension) == 192);
+    assert!(offset_of!(TextureTransform, color_adjust) == 208);
+    assert!(offset_of!(TextureTransform, pattern_offset) == 224);
+    assert!(offset_of!(TextureTransform, surface_size) == 232);
+};
+
+pub const PATTERN_SHADER: &str = include_str!("shader.wgsl");
+
+pub const COLOR_ADJUST_IDENTITY: [f32; 4] = [1.0, 1.0, 1.0, 0.0];
+
+/// The target size uniforms start out with unless told otherwise.
+pub const DEFAULT_SURFACE_SIZE: [f32; 2] = [640.0, 360.0];
+
+const IDENTITY: [[f32; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// Identity transforms over a 1x1 image; unlike `Zeroable::zeroed` this is
+/// something the shader can sample through.
+impl Default for TextureTransform {
+    fn default() -> Self {
+        Self::identity(1.0, 1.0)
+    }
+}
+
+impl TextureTransform {
+    pub fn identity(image_width: f32, image_height: f32) -> Self {
+        Self {
+            transform: IDENTITY,
+            bitmap_transform: IDENTITY,
+            inverse_bitmap_transform: IDENTITY,
+            image_dimension: [image_width, image_height, 0.0, 1.0],
+            color_adjust: COLOR_ADJUST_IDENTITY,
+            pattern_offset: [0.0, 0.0],
+            surface_size: DEFAULT_SURFACE_SIZE,
+        }
+    }
+
+    pub fn from_trs(
+        translation: [f32; 2],
+        rotation_radians: f32,
+        scale: [f32; 2],
+        image_dims: [f32; 2],
+    ) -> Result<Self> {
+        let (sin, cos) = rotation_radians.sin_cos();
+        let bitmap_transform = [
+            [cos * scale[0], sin * scale[0], 0.0, 0.0],
+            [-sin * scale[1], cos * scale[1], 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [translation[0], translation[1], 0.0, 1.0],
+        ];
+        let inverse_bitmap_transform =
+            inverse(bitmap_transform).ok_or(TextureError::NonInvertibleTransform)?;
+        Ok(Self {
+            transform: IDENTITY,
+            bitmap_transform,
+            inverse_bitmap_transform,
+            image_dimension: [image_dims[0], image_dims[1], 0.0, 1.0],
+            color_adjust: COLOR_ADJUST_IDENTITY,
+            pattern_offset: [0.0, 0.0],
+            surface_size: DEFAULT_SURFACE_SIZE,
+        })
+    }
+
+    pub fn set_bitmap_transform(&mut self, bitmap_transform: [[f32; 4]; 4]) -> Result<()> {
+        let previous = std::mem::replace(&mut self.bitmap_transform, bitmap_transform);
+        if let Err(e) = self.recompute_inverse() {
+            self.bitmap_transform = previous;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Call after editing `bitmap_transform` directly, the shader samples
+    /// through the inverse.
+    pub fn recompute_inverse(&mut self) -> Result<()> {
+        self.inverse_bitmap_transform =
+            inverse(self.bitmap_transform).ok_or(TextureError::NonInvertibleTransform)?;
+        Ok(())
+    }
+}
+
+impl TextureTransform {
+    pub fn from_affine2d(m: [f32; 6], image_dims: [f32; 2]) -> Result<Self> {
+        let [a, b, c, d, e, f] = m;
+        let affine = [[a, b, 0.0], [c, d, 0.0], [e, f, 1.0]];
+        let inv = inverse3(affine).ok_or(TextureError::NonInvertibleTransform)?;
+        let bitmap_transform = embed_affine2d(affine);
+        let inverse_bitmap_transform = embed_affine2d(inv);
+        Ok(Self {
+            transform: IDENTITY,
+            bitmap_transform,
+            inverse_bitmap_transform,
+            image_dimension: [image_dims[0], image_dims[1], 0.0, 1.0],
+            color_adjust: COLOR_ADJUST_IDENTITY,
+            pattern_offset: [0.0, 0.0],
+            surface_size: DEFAULT_SURFACE_SIZE,
+        })
+    }
+}
+
+#[cfg(feature = "glam")]
+impl TextureTransform {
+    pub fn from_mats(
+        transform: glam::Mat4,
+        bitmap_transform: glam::Mat4,
+        image_width: f32,
+        image_height: f32,
+    ) -> Result<Self> {
+        let precise = bitmap_transform.as_dmat4();
+        if inverse(bitmap_transform.to_cols_array_2d()).is_none() {
+            return Err(TextureError::NonInvertibleTransform);
+        }
+        Ok(Self {
+            transform: transform.to_cols_array_2d(),
+            bitmap_transform: bitmap_transform.to_cols_array_2d(),
+            inverse_bitmap_transform: precise.inverse().as_mat4().to_cols_array_2d(),
+            image_dimension: [image_width, image_height, 0.0, 1.0],
+            color_adjust: COLOR_ADJUST_IDENTITY,
+            pattern_offset: [0.0, 0.0],
+            surface_size: DEFAULT_SURFACE_SIZE,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Repetition {
+    #[default]
+    Repeat,
+    RepeatX,
+    RepeatY,
+    NoRepeat,
+    /// Samples outside the image return the border colour, e.g. a transparent
+    /// surround. Needs `Features::ADDRESS_MODE_CLAMP_TO_BORDER`.
+    ClampToBorder(wgpu::SamplerBorderColor),
+}
+
+impl Repetition {
+    fn address_modes(self) -> (wgpu::AddressMode, wgpu::AddressMode) {
+        use wgpu::AddressMode::{ClampToBorder, ClampToEdge, Repeat};
+        match self {
+            Repetition::Repeat => (Repeat, Repeat),
+            Repetition::RepeatX => (Repeat, ClampToEdge),
+            Repetition::RepeatY => (ClampToEdge, Repeat),
+            Repetition::NoRepeat => (ClampToEdge, ClampToEdge),
+            Repetition::ClampToBorder(_) => (ClampToBorder, ClampToBorder),
+        }
+    }
+
+    fn border_color(self) -> Option<wgpu::SamplerBorderColor> {
+        match self {
+            Repetition::ClampToBorder(color) => Some(color),
+            _ => None,
+        }
+    }
+
+    fn required_features(self) -> wgpu::Features {
+        match self {
+            Repetition::ClampToBorder(wgpu::SamplerBorderColor::Zero) => {
+                wgpu::Features::ADDRESS_MODE_CLAMP_TO_ZERO
+            }
+            Repetition::ClampToBorder(_) => wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER,
+            _ => wgpu::Features::empty(),
+        }
+    }
+}
+
+/// Triangle winding of the quad as it lands in clip space. The shader flips
+/// the y axis when mapping pixel positions to clip space, so `Ccw` is the
+/// winding to pair with the default `FrontFace::Ccw` and `cull_mode:
+/// Some(Face::Back)`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Winding {
+    #[default]
+    Ccw,
+    Cw,
+}
+
+impl Winding {
+    pub fn indices(self) -> [u16; 6] {
+        match self {
+            Winding::Ccw => [0, 2, 1, 0, 3, 2],
+            Winding::Cw => [0, 1, 2, 0, 2, 3],
+        }
+    }
+}
+
+/// How the pattern pipeline composites onto the target, after canvas's
+/// `globalCompositeOperation`. `PremultipliedOver` expects textures built with
+/// `premultiply_alpha(true)`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    #[default]
+    SourceOver,
+    Additive,
+    PremultipliedOver,
+}
+
+impl BlendMode {
+    fn blend_state(self) -> wgpu::BlendState {
+        match self {
+            BlendMode::SourceOver => wgpu::BlendState::ALPHA_BLENDING,
+            BlendMode::Additive => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            },
+            BlendMode::PremultipliedOver => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+        }
+    }
+}
+
+/// Tone mapping applied to linear sources when they are squeezed into an 8-bit
+/// texture. `None` just clamps, which blows HDR highlights out to white.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum ColorTransform {
+    #[default]
+    None,
+    Reinhard,
+    AcesApprox,
+}
+
+impl ColorTransform {
+    fn apply(self, c: f32) -> f32 {
+        match self {
+            ColorTransform::None => c,
+            ColorTransform::Reinhard => c / (1.0 + c),
+            // Krzysztof Narkowicz's fit of the ACES filmic curve.
+            ColorTransform::AcesApprox => (c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14),
+        }
+    }
+}
+
+/// How the image is fitted into the quad when their aspect ratios differ,
+/// like CSS `object-fit`. `Fill` stretches, `Contain` shows all of the image
+/// with uvs outside 0..1 on the short side (pair it with `NoRepeat` or
+/// `ClampToBorder` for letterboxing) and `Cover` crops the long side.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Fit {
+    #[default]
+    Fill,
+    Contain,
+    Cover,
+}
+
+impl Fit {
+    /// Per-axis factors to scale uvs by about the centre.
+    fn uv_scale(self, image_aspect: f32, quad_aspect: f32) -> [f32; 2] {
+        let (u, v) = (quad_aspect / image_aspect, image_aspect / quad_aspect);
+        match self {
+            Fit::Fill => [1.0, 1.0],
+            Fit::Contain => [u.max(1.0), v.max(1.0)],
+            Fit::Cover => [u.min(1.0), v.min(1.0)],
+        }
+    }
+}
+
+/// Resampling filter for `TextureBuilder::max_dimension`, fastest first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum ResizeFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    #[default]
+    Lanczos3,
+}
+
+impl ResizeFilter {
+    fn filter_type(self) -> image::imageops::FilterType {
+        match self {
+            ResizeFilter::Nearest => image::imageops::FilterType::Nearest,
+            ResizeFilter::Triangle => image::imageops::FilterType::Triangle,
+            ResizeFilter::CatmullRom => image::imageops::FilterType::CatmullRom,
+            ResizeFilter::Lanczos3 => image::imageops::FilterType::Lanczos3,
+        }
+    }
+}
+
+/// Where the origin sits on the generated quad; `Center` makes `transform`
+/// rotate and scale the pattern about its middle.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Center,
+}
+
+impl Anchor {
+    fn offset(self, width: f32, height: f32) -> [f32; 2] {
+        match self {
+            Anchor::TopLeft => [0.0, 0.0],
+            Anchor::Center => [width / 2.0, height / 2.0],
+        }
+    }
 }
 
 impl Vertex {
@@ -51,63 +540,641 @@ impl Vertex {
     }
 }
 
-fn inverse(a: [[f32; 4]; 4]) -> Option<[[f32; 4]; 4]> {
+impl VertexColored {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<VertexColored>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+impl TileInstance {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TileInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[wgpu::VertexAttribute {
+                offset: 0,
+                shader_location: 3,
+                format: wgpu::VertexFormat::Float32x2,
+            }],
+        }
+    }
+}
+
+pub fn quad(width: f32, height: f32) -> ([Vertex; 4], [u16; 6]) {
+    let x = width;
+    let y = height;
+
+    let vertices = [
+        Vertex {
+            position: [0.0, 0.0, 0.0],
+            tex_coords: [0.0, 0.0],
+        }, // A
+        Vertex {
+            position: [x, 0.0, 0.0],
+            tex_coords: [1.0, 0.0],
+        }, // B
+        Vertex {
+            position: [x, y, 0.0],
+            tex_coords: [1.0, 1.0],
+        }, // C
+        Vertex {
+            position: [0.0, y, 0.0],
+            tex_coords: [0.0, 1.0],
+        }, // D
+    ];
+    (vertices, Winding::default().indices())
+}
+
+/// Evaluates the pattern at a quad uv the same way the shader and sampler do:
+/// uv is scaled to image pixels, mapped through `inverse_bitmap_transform`,
+/// normalised again and then wrapped per `repetition` and bilinearly filtered.
+/// Filtering happens on the stored 8-bit values, so results for sRGB textures
+/// can differ from the GPU by a rounding step or two.
+pub fn sample_cpu(
+    img: &image::DynamicImage,
+    transform: &TextureTransform,
+    uv: [f32; 2],
+    repetition: Repetition,
+) -> [u8; 4] {
+    use image::GenericImageView;
+
+    let [dim_x, dim_y, _, _] = transform.image_dimension;
+    let m = transform.inverse_bitmap_transform;
+    let (px, py) = (uv[0] * dim_x, uv[1] * dim_y);
+    let [offset_x, offset_y] = transform.pattern_offset;
+    let tex_x = (m[0][0] * px + m[1][0] * py + m[3][0]) / dim_x - offset_x;
+    let tex_y = (m[0][1] * px + m[1][1] * py + m[3][1]) / dim_y - offset_y;
+
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return [0; 4];
+    }
+    // Each axis wraps on its own with the address modes the GPU sampler is
+    // given, so `RepeatX` wraps u but clamps v and `RepeatY` the reverse.
+    let (mode_u, mode_v) = repetition.address_modes();
+    let border = match repetition.border_color() {
+        Some(wgpu::SamplerBorderColor::OpaqueBlack) => [0.0, 0.0, 0.0, 255.0],
+        Some(wgpu::SamplerBorderColor::OpaqueWhite) => [255.0; 4],
+        _ => [0.0; 4],
+    };
+    let wrap = |i: i64, size: u32, mode: wgpu::AddressMode| match mode {
+        wgpu::AddressMode::Repeat => Some(i.rem_euclid(size as i64) as u32),
+        wgpu::AddressMode::ClampToBorder if i < 0 || i >= size as i64 => None,
+        _ => Some(i.clamp(0, size as i64 - 1) as u32),
+    };
+    let fetch = |x: i64, y: i64| match (wrap(x, width, mode_u), wrap(y, height, mode_v)) {
+        (Some(x), Some(y)) => img.get_pixel(x, y).0.map(f32::from),
+        _ => border,
+    };
+
+    // Texel centres sit at half-integer coordinates.
+    let x = tex_x * width as f32 - 0.5;
+    let y = tex_y * height as f32 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let (c00, c10) = (fetch(x0, y0), fetch(x0 + 1, y0));
+    let (c01, c11) = (fetch(x0, y0 + 1), fetch(x0 + 1, y0 + 1));
+
+    let mut out = [0; 4];
+    for i in 0..4 {
+        let top = c00[i] + (c10[i] - c00[i]) * fx;
+        let bottom = c01[i] + (c11[i] - c01[i]) * fx;
+        out[i] = (top + (bottom - top) * fy).round().clamp(0.0, 255.0) as u8;
+    }
+    out
+}
+
+/// Element-wise comparison with an absolute tolerance, for checking matrix
+/// maths where exact float equality is too strict.
+#[cfg(any(test, feature = "test-util"))]
+pub fn mat_approx_eq(a: [[f32; 4]; 4], b: [[f32; 4]; 4], eps: f32) -> bool {
+    a.iter()
+        .flatten()
+        .zip(b.iter().flatten())
+        .all(|(x, y)| (x - y).abs() <= eps)
+}
+
+/// Matrices whose smallest elimination pivot is this small relative to the
+/// largest are treated as singular; the inverse would be dominated by rounding
+/// error. Being relative, uniformly small scales still invert.
+pub const SINGULAR_EPSILON: f32 = 1e-8;
+
+/// Gauss-Jordan elimination with partial pivoting in double precision.
+/// Returns the inverse, the determinant and the ratio of the smallest to the
+/// largest pivot, or `None` if a pivot is exactly zero.
+fn gauss_jordan<const N: usize>(a: [[f32; N]; N]) -> Option<([[f64; N]; N], f64, f64)> {
+    // Eliminate on rows, so an affine matrix's translation column is never
+    // picked as a pivot; the result comes out transposed back.
+    let mut m: [[f64; N]; N] = std::array::from_fn(|i| std::array::from_fn(|j| a[j][i] as f64));
+    let mut out: [[f64; N]; N] =
+        std::array::from_fn(|i| std::array::from_fn(|j| if i == j { 1.0 } else { 0.0 }));
+    let mut det = 1.0;
+    let (mut min_pivot, mut max_pivot) = (f64::INFINITY, 0.0f64);
+    for i in 0..N {
+        let pivot = (i..N)
+            .max_by(|&x, &y| m[x][i].abs().total_cmp(&m[y][i].abs()))
+            .unwrap();
+        if pivot != i {
+            m.swap(pivot, i);
+            out.swap(pivot, i);
+            det = -det;
+        }
+        let p = m[i][i];
+        if p == 0.0 || !p.is_finite() {
+            return None;
+        }
+        det *= p;
+        min_pivot = min_pivot.min(p.abs());
+        max_pivot = max_pivot.max(p.abs());
+        for k in 0..N {
+            m[i][k] /= p;
+            out[i][k] /= p;
+        }
+        for j in 0..N {
+            if j != i {
+                let f = m[j][i];
+                for k in 0..N {
+                    m[j][k] -= f * m[i][k];
+                    out[j][k] -= f * out[i][k];
+                }
+            }
+        }
+    }
+    let out = std::array::from_fn(|i| std::array::from_fn(|j| out[j][i]));
+    Some((out, det, min_pivot / max_pivot))
+}
+
+/// `a * b` for column-major matrices, so `b` applies first. Together with
+/// `inverse` this is enough to compose transforms without a maths crate.
+pub fn mul4(a: [[f32; 4]; 4], b: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
     let mut out = [[0.0; 4]; 4];
-    let a00 = a[0][0];
-    let a01 = a[0][1];
-    let a02 = a[0][2];
-    let a03 = a[0][3];
-    let a10 = a[1][0];
-    let a11 = a[1][1];
-    let a12 = a[1][2];
-    let a13 = a[1][3];
-    let a20 = a[2][0];
-    let a21 = a[2][1];
-    let a22 = a[2][2];
-    let a23 = a[2][3];
-    let a30 = a[3][0];
-    let a31 = a[3][1];
-    let a32 = a[3][2];
-    let a33 = a[3][3];
-    let b00 = a00 * a11 - a01 * a10;
-    let b01 = a00 * a12 - a02 * a10;
-    let b02 = a00 * a13 - a03 * a10;
-    let b03 = a01 * a12 - a02 * a11;
-    let b04 = a01 * a13 - a03 * a11;
-    let b05 = a02 * a13 - a03 * a12;
-    let b06 = a20 * a31 - a21 * a30;
-    let b07 = a20 * a32 - a22 * a30;
-    let b08 = a20 * a33 - a23 * a30;
-    let b09 = a21 * a32 - a22 * a31;
-    let b10 = a21 * a33 - a23 * a31;
-    let b11 = a22 * a33 - a23 * a32;
-    // Calculate the determinant
-    let det = b00 * b11 - b01 * b10 + b02 * b09 + b03 * b08 - b04 * b07 + b05 * b06;
-
-    if det == 0.0 {
+    for (col, b_col) in out.iter_mut().zip(b) {
+        for (row, value) in col.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b_col[k]).sum();
+        }
+    }
+    out
+}
+
+/// `None` when the matrix is singular, see `SINGULAR_EPSILON`.
+pub fn inverse(a: [[f32; 4]; 4]) -> Option<[[f32; 4]; 4]> {
+    inverse_f64(a, SINGULAR_EPSILON)
+}
+
+/// `inverse` with a custom singularity threshold, see `SINGULAR_EPSILON`.
+/// Works in double precision and narrows back to f32; large scale
+/// differences between bitmap and world space lose too much accuracy in
+/// single precision.
+pub fn inverse_f64(a: [[f32; 4]; 4], epsilon: f32) -> Option<[[f32; 4]; 4]> {
+    let (out, _, pivot_ratio) = gauss_jordan(a)?;
+    if pivot_ratio <= epsilon as f64 {
+        return None;
+    }
+    Some(out.map(|col| col.map(|v| v as f32)))
+}
+
+/// Negative for mirrored transforms; the magnitude is the area scale for
+/// transforms that leave z alone.
+/// Computed in double precision from the same elimination as `inverse`, and
+/// 0.0 for any matrix `inverse` rejects as singular.
+pub fn determinant4(a: [[f32; 4]; 4]) -> f32 {
+    gauss_jordan(a)
+        .filter(|&(_, _, pivot_ratio)| pivot_ratio > SINGULAR_EPSILON as f64)
+        .map_or(0.0, |(_, det, _)| det as f32)
+}
+
+/// The 3x3 counterpart of `inverse`, for 2D affine matrices.
+fn inverse3(a: [[f32; 3]; 3]) -> Option<[[f32; 3]; 3]> {
+    let (out, _, pivot_ratio) = gauss_jordan(a)?;
+    if pivot_ratio <= SINGULAR_EPSILON as f64 {
         return None;
     }
+    Some(out.map(|col| col.map(|v| v as f32)))
+}
+
+/// Box-filters each level down from the previous one until reaching 1x1. The
+/// base level itself is not included. With `srgb` the colour channels are
+/// averaged in linear space, otherwise the mips come out too dark.
+fn mip_chain<C>(base: &image::ImageBuffer<image::Rgba<u8>, C>, srgb: bool) -> Vec<image::RgbaImage>
+where
+    C: std::ops::Deref<Target = [u8]>,
+{
+    let mut levels: Vec<image::RgbaImage> = Vec::new();
+    let (mut width, mut height) = base.dimensions();
+    while width > 1 || height > 1 {
+        let next = match levels.last() {
+            Some(prev) => downsample(prev, srgb),
+            None => downsample(base, srgb),
+        };
+        width = next.width();
+        height = next.height();
+        levels.push(next);
+    }
+    levels
+}
+
+fn downsample<C>(src: &image::ImageBuffer<image::Rgba<u8>, C>, srgb: bool) -> image::RgbaImage
+where
+    C: std::ops::Deref<Target = [u8]>,
+{
+    let width = (src.width() / 2).max(1);
+    let height = (src.height() / 2).max(1);
+    let to_linear: [f32; 256] = std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0));
+    image::RgbaImage::from_fn(width, height, |x, y| {
+        let mut sum = [0u32; 4];
+        let mut linear = [0f32; 3];
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let sx = (x * 2 + dx).min(src.width() - 1);
+            let sy = (y * 2 + dy).min(src.height() - 1);
+            let p = src.get_pixel(sx, sy);
+            for c in 0..4 {
+                sum[c] += p[c] as u32;
+            }
+            for c in 0..3 {
+                linear[c] += to_linear[p[c] as usize];
+            }
+        }
+        let mut out = sum.map(|c| ((c + 2) / 4) as u8);
+        if srgb {
+            for c in 0..3 {
+                out[c] = (linear_to_srgb(linear[c] / 4.0) * 255.0).round() as u8;
+            }
+        }
+        image::Rgba(out)
+    })
+}
+
+fn premultiply_rgba8(pixels: &mut [u8]) {
+    for p in pixels.chunks_exact_mut(4) {
+        let a = p[3] as u32;
+        for c in &mut p[..3] {
+            *c = ((*c as u32 * a + 127) / 255) as u8;
+        }
+    }
+}
+
+/// sRGB-encoded colour has to be premultiplied in linear space, otherwise
+/// half-transparent edges come out too dark.
+fn premultiply_srgb8(pixels: &mut [u8]) {
+    for p in pixels.chunks_exact_mut(4) {
+        let a = p[3] as f32 / 255.0;
+        for c in &mut p[..3] {
+            let linear = srgb_to_linear(*c as f32 / 255.0) * a;
+            *c = (linear_to_srgb(linear) * 255.0).round() as u8;
+        }
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Tone maps linear RGB and encodes it for the target texture, leaving alpha
+/// alone.
+fn tone_map_rgba8(
+    img: &image::DynamicImage,
+    transform: ColorTransform,
+    srgb: bool,
+) -> image::RgbaImage {
+    let linear = img.to_rgba32f();
+    let mut out = image::RgbaImage::new(linear.width(), linear.height());
+    for (src, dst) in linear.pixels().zip(out.pixels_mut()) {
+        for i in 0..3 {
+            let mut c = transform.apply(src[i].max(0.0)).clamp(0.0, 1.0);
+            if srgb {
+                c = linear_to_srgb(c);
+            }
+            dst[i] = (c * 255.0).round() as u8;
+        }
+        dst[3] = (src[3].clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+    out
+}
+
+/// Copies tightly packed RGBA8 rows of `width` pixels into the top-left of a
+/// zeroed `padded_width` x `padded_height` image.
+fn pad_rgba8(pixels: &[u8], width: u32, padded_width: u32, padded_height: u32) -> Vec<u8> {
+    let row_len = width as usize * 4;
+    let mut padded = vec![0; padded_width as usize * padded_height as usize * 4];
+    for (src, dst) in pixels
+        .chunks_exact(row_len)
+        .zip(padded.chunks_exact_mut(padded_width as usize * 4))
+    {
+        dst[..row_len].copy_from_slice(src);
+    }
+    padded
+}
+
+fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).leading_zeros()
+}
+
+/// Writes the base level and, if the texture was allocated with them, the
+/// generated mip levels.
+fn write_rgba8(
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    layer: u32,
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+) {
+    let origin = wgpu::Origin3d {
+        x: 0,
+        y: 0,
+        z: layer,
+    };
+    write_level(queue, texture, 0, origin, pixels, width, height);
+    if texture.mip_level_count() > 1 {
+        let base =
+            image::ImageBuffer::<image::Rgba<u8>, _>::from_raw(width, height, pixels).unwrap();
+        for (i, level) in mip_chain(&base, texture.format().describe().srgb)
+            .iter()
+            .enumerate()
+        {
+            write_level(
+                queue,
+                texture,
+                i as u32 + 1,
+                origin,
+                level,
+                level.width(),
+                level.height(),
+            );
+        }
+    }
+}
+
+/// `write_rgba8` recorded on the caller's encoder instead of the queue, with
+/// `staging_label` on the staging buffers.
+fn copy_rgba8(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    staging_label: &str,
+    texture: &wgpu::Texture,
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+) {
+    let base = image::ImageBuffer::<image::Rgba<u8>, _>::from_raw(width, height, pixels).unwrap();
+    copy_level(device, encoder, staging_label, texture, 0, &base);
+    if texture.mip_level_count() > 1 {
+        for (i, level) in mip_chain(&base, texture.format().describe().srgb)
+            .iter()
+            .enumerate()
+        {
+            copy_level(device, encoder, staging_label, texture, i as u32 + 1, level);
+        }
+    }
+}
+
+/// Stages one RGBA8 level in a buffer with rows padded to
+/// `COPY_BYTES_PER_ROW_ALIGNMENT` and records the copy into the texture. wgpu
+/// keeps the buffer alive until the copy has run.
+fn copy_level<C>(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    staging_label: &str,
+    texture: &wgpu::Texture,
+    mip_level: u32,
+    level: &image::ImageBuffer<image::Rgba<u8>, C>,
+) where
+    C: std::ops::Deref<Target = [u8]>,
+{
+    let (width, height) = level.dimensions();
+    let pixels: &[u8] = level;
+    let row_len = width as usize * 4;
+    let bytes_per_row = aligned_bytes_per_row(width, 4);
+    let mut staged = vec![0; bytes_per_row as usize * height as usize];
+    for (src, dst) in pixels
+        .chunks_exact(row_len)
+        .zip(staged.chunks_exact_mut(bytes_per_row as usize))
+    {
+        dst[..row_len].copy_from_slice(src);
+    }
+    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(staging_label),
+        contents: &staged,
+        usage: wgpu::BufferUsages::COPY_SRC,
+    });
+    encoder.copy_buffer_to_texture(
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(bytes_per_row),
+                rows_per_image: NonZeroU32::new(height),
+            },
+        },
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+fn check_rgba8_len(pixels: &[u8], width: u32, height: u32) -> Result<()> {
+    let expected = width as usize * height as usize * 4;
+    if pixels.len() != expected {
+        return Err(TextureError::InvalidLength {
+            expected,
+            actual: pixels.len(),
+        });
+    }
+    Ok(())
+}
 
-    let det = 1.0 / det;
+/// Row pitch for buffer/texture copies, which must be a multiple of
+/// `COPY_BYTES_PER_ROW_ALIGNMENT` unlike `queue.write_texture`.
+pub fn aligned_bytes_per_row(width: u32, bytes_per_pixel: u32) -> u32 {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    (width * bytes_per_pixel).div_ceil(align) * align
+}
+
+/// Writes one mip level of tightly packed texel data, computing the row pitch
+/// from the texture's format.
+fn write_level(
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    mip_level: u32,
+    origin: wgpu::Origin3d,
+    data: &[u8],
+    width: u32,
+    height: u32,
+) {
+    let info = texture.format().describe();
+    let (block_width, block_height) = info.block_dimensions;
+    let blocks_wide = width.div_ceil(block_width as u32);
+    let blocks_high = height.div_ceil(block_height as u32);
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level,
+            origin,
+        },
+        data,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(blocks_wide * info.block_size as u32),
+            rows_per_image: NonZeroU32::new(blocks_high),
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+/// Embeds a column-major 2D affine matrix into the xy plane of a 4x4 matrix.
+fn embed_affine2d(m: [[f32; 3]; 3]) -> [[f32; 4]; 4] {
+    [
+        [m[0][0], m[0][1], 0.0, 0.0],
+        [m[1][0], m[1][1], 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [m[2][0], m[2][1], 0.0, 1.0],
+    ]
+}
+
+/// `"{label} {resource}"`, or `fallback` for unlabelled textures, so GPU
+/// captures show which texture a resource belongs to.
+fn resource_label(label: Option<&str>, resource: &str, fallback: &str) -> String {
+    match label {
+        Some(label) => format!("{} {}", label, resource),
+        None => fallback.to_owned(),
+    }
+}
+
+/// The sampler's label, which stays unset for unlabelled textures.
+fn sampler_label(label: Option<&str>) -> Option<String> {
+    label.map(|label| format!("{} sampler", label))
+}
 
-    out[0][0] = (a11 * b11 - a12 * b10 + a13 * b09) * det;
-    out[0][1] = (a02 * b10 - a01 * b11 - a03 * b09) * det;
-    out[0][2] = (a31 * b05 - a32 * b04 + a33 * b03) * det;
-    out[0][3] = (a22 * b04 - a21 * b05 - a23 * b03) * det;
-    out[1][0] = (a12 * b08 - a10 * b11 - a13 * b07) * det;
-    out[1][1] = (a00 * b11 - a02 * b08 + a03 * b07) * det;
-    out[1][2] = (a32 * b02 - a30 * b05 - a33 * b01) * det;
-    out[1][3] = (a20 * b05 - a22 * b02 + a23 * b01) * det;
-    out[2][0] = (a10 * b10 - a11 * b08 + a13 * b06) * det;
-    out[2][1] = (a01 * b08 - a00 * b10 - a03 * b06) * det;
-    out[2][2] = (a30 * b04 - a31 * b02 + a33 * b00) * det;
-    out[2][3] = (a21 * b02 - a20 * b04 - a23 * b00) * det;
-    out[3][0] = (a11 * b07 - a10 * b09 - a12 * b06) * det;
-    out[3][1] = (a00 * b09 - a01 * b07 + a02 * b06) * det;
-    out[3][2] = (a31 * b01 - a30 * b03 - a32 * b00) * det;
-    out[3][3] = (a20 * b03 - a21 * b01 + a22 * b00) * det;
+/// Rejects empty textures and sizes beyond what `limits` allow, before
+/// anything is created on the device.
+fn check_extent(width: u32, height: u32, layers: u32, limits: &wgpu::Limits) -> Result<()> {
+    if width == 0 || height == 0 {
+        return Err(TextureError::Empty {
+            width,
+            height,
+            layers,
+        });
+    }
+    let max = limits.max_texture_dimension_2d;
+    if width > max || height > max {
+        return Err(TextureError::TooLarge {
+            requested: width.max(height),
+            max,
+        });
+    }
+    if layers > limits.max_texture_array_layers {
+        return Err(TextureError::TooLarge {
+            requested: layers,
+            max: limits.max_texture_array_layers,
+        });
+    }
+    Ok(())
+}
 
-    Some(out)
+/// A comparison sampler pairs with a depth texture binding, i.e.
+/// `texture_depth_2d` and `sampler_comparison` on the shader side.
+/// Multisampled textures can't be filtered, so they get a non-filtering
+/// float binding, i.e. `texture_multisampled_2d<f32>` read with
+/// `textureLoad`; the texture's own sampler stays bound next to it.
+fn layout_entries(
+    view_dimension: wgpu::TextureViewDimension,
+    has_dynamic_offset: bool,
+    comparison: bool,
+    multisampled: bool,
+) -> [wgpu::BindGroupLayoutEntry; 3] {
+    let (sample_type, sampler_type) = if comparison {
+        (
+            wgpu::TextureSampleType::Depth,
+            wgpu::SamplerBindingType::Comparison,
+        )
+    } else if multisampled {
+        (
+            wgpu::TextureSampleType::Float { filterable: false },
+            wgpu::SamplerBindingType::Filtering,
+        )
+    } else {
+        (
+            wgpu::TextureSampleType::Float { filterable: true },
+            wgpu::SamplerBindingType::Filtering,
+        )
+    };
+    [
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled,
+                view_dimension,
+                sample_type,
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(sampler_type),
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 2,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset,
+                min_binding_size: wgpu::BufferSize::new(
+                    std::mem::size_of::<TextureTransform>() as u64
+                ),
+            },
+            count: None,
+        },
+    ]
 }
 
 impl Texture {
@@ -116,37 +1183,1713 @@ impl Texture {
         queue: &wgpu::Queue,
         bytes: &[u8],
         label: &str,
+        repetition: Repetition,
     ) -> Result<Self> {
         let img = image::load_from_memory(bytes)?;
-        Self::from_image(device, queue, &img, Some(label))
+        Self::from_image(device, queue, &img, Some(label), repetition, None)
     }
 
-    pub fn from_image(
+    /// Decodes as `format` instead of sniffing it, for headerless data or
+    /// when the format is already known from e.g. a content type.
+    pub fn from_bytes_with_format(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        img: &image::DynamicImage,
+        bytes: &[u8],
+        format: image::ImageFormat,
+        label: &str,
+    ) -> Result<Self> {
+        let img = image::load_from_memory_with_format(bytes, format)?;
+        let mut builder = TextureBuilder::new();
+        builder.label = Some(label);
+        builder.build(device, queue, &img)
+    }
+
+    pub fn from_path(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
         label: Option<&str>,
+        repetition: Repetition,
     ) -> Result<Self> {
-        let rgba = img.to_rgba8();
-        let dimensions = img.dimensions();
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|source| TextureError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        let img = image::load_from_memory(&bytes)?;
+        Self::from_image(device, queue, &img, label, repetition, None)
+    }
 
-        let size = wgpu::Extent3d {
-            width: dimensions.0,
-            height: dimensions.1,
-            depth_or_array_layers: 1,
+    /// Like `from_path`, but decodes straight from a memory map of the file
+    /// instead of reading it into a buffer first, so very large sources don't
+    /// sit in memory twice. The map is dropped as soon as decoding finishes.
+    #[cfg(feature = "memmap")]
+    pub fn from_path_mmap(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
+        label: Option<&str>,
+        repetition: Repetition,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let io_error = |source| TextureError::Io {
+            path: path.to_owned(),
+            source,
         };
-        let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+        let file = std::fs::File::open(path).map_err(io_error)?;
+        // Safety: the map is only read while decoding below. Truncating or
+        // rewriting the file from elsewhere in the meantime is undefined
+        // behaviour, as with any file-backed map.
+        let map = unsafe { memmap2::Mmap::map(&file) }.map_err(io_error)?;
+        let img = image::load_from_memory(&map)?;
+        drop(map);
+        Self::from_image(device, queue, &img, label, repetition, None)
+    }
+
+    pub fn builder<'a>() -> TextureBuilder<'a> {
+        TextureBuilder::new()
+    }
+
+    /// `bitmap_transform` places the pattern within the quad, `None` for
+    /// identity. Its inverse is computed here and fails the build if singular.
+    pub fn from_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        repetition: Repetition,
+        bitmap_transform: Option<[[f32; 4]; 4]>,
+    ) -> Result<Self> {
+        let mut builder = TextureBuilder::new()
+            .repetition(repetition)
+            .bitmap_transform(bitmap_transform.unwrap_or(IDENTITY));
+        builder.label = label;
+        builder.build(device, queue, img)
+    }
+
+    pub fn from_image_mipmapped(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        repetition: Repetition,
+    ) -> Result<Self> {
+        let mut builder = TextureBuilder::new().repetition(repetition).mipmaps(true);
+        builder.label = label;
+        builder.build(device, queue, img)
+    }
+
+    pub fn from_image_with_size(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        repetition: Repetition,
+        width: f32,
+        height: f32,
+    ) -> Result<Self> {
+        let mut builder = TextureBuilder::new()
+            .repetition(repetition)
+            .size(width, height);
+        builder.label = label;
+        builder.build(device, queue, img)
+    }
+
+    pub fn from_rgba8(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        label: Option<&str>,
+    ) -> Result<Self> {
+        let mut builder = TextureBuilder::new();
+        builder.label = label;
+        builder.build_rgba8(device, queue, pixels, width, height)
+    }
+
+    /// Records the upload on `encoder` via a staging buffer rather than going
+    /// through `queue.write_texture`, so it lands in order with the rest of
+    /// the encoder's work once that is submitted.
+    pub fn from_image_with_encoder(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+    ) -> Result<Self> {
+        let mut builder = TextureBuilder::new();
+        builder.label = label;
+        builder.build_with_encoder(device, encoder, img)
+    }
+
+    /// Uploads an RgbaImage's buffer as-is, skipping the DynamicImage round trip.
+    pub fn from_rgba_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::RgbaImage,
+        label: Option<&str>,
+    ) -> Result<Self> {
+        Self::from_rgba8(
+            device,
+            queue,
+            img.as_raw(),
+            img.width(),
+            img.height(),
+            label,
+        )
+    }
+
+    pub fn from_bgra8(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        label: Option<&str>,
+    ) -> Result<Self> {
+        let mut builder = TextureBuilder::new();
+        builder.label = label;
+        builder.build_bgra8(device, queue, pixels, width, height)
+    }
+
+    pub fn from_rgba8_strided(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        stride: u32,
+        label: Option<&str>,
+    ) -> Result<Self> {
+        let mut builder = TextureBuilder::new();
+        builder.label = label;
+        builder.build_rgba8_strided(device, queue, pixels, width, height, stride)
+    }
+
+    pub fn checkerboard(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: (u32, u32),
+        cell: u32,
+        color_a: [u8; 4],
+        color_b: [u8; 4],
+    ) -> Result<Self> {
+        if cell == 0 {
+            return Err(TextureError::Invalid(
+                "checkerboard cell size must not be zero".into(),
+            ));
+        }
+        let (width, height) = size;
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height {
+            for x in 0..width {
+                let color = if (x / cell + y / cell).is_multiple_of(2) {
+                    color_a
+                } else {
+                    color_b
+                };
+                pixels.extend_from_slice(&color);
+            }
+        }
+        Self::from_rgba8(device, queue, &pixels, width, height, Some("checkerboard"))
+    }
+
+    pub fn solid(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: (u32, u32),
+        color: [u8; 4],
+    ) -> Result<Self> {
+        let (width, height) = size;
+        let pixels = color.repeat(width as usize * height as usize);
+        Self::from_rgba8(device, queue, &pixels, width, height, Some("solid"))
+    }
+
+    pub fn from_image_hdr(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+    ) -> Result<Self> {
+        let mut builder = TextureBuilder::new();
+        builder.label = label;
+        builder.build_hdr(device, queue, img)
+    }
+
+    pub fn from_mip_levels(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        levels: &[(&[u8], u32, u32)],
+        format: wgpu::TextureFormat,
+        label: Option<&str>,
+    ) -> Result<Self> {
+        let mut builder = TextureBuilder::new();
+        builder.label = label;
+        builder.build_mip_levels(device, queue, format, levels)
+    }
+
+    pub fn from_images(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        imgs: &[image::DynamicImage],
+        label: Option<&str>,
+    ) -> Result<Self> {
+        let mut builder = TextureBuilder::new();
+        builder.label = label;
+        builder.build_array(device, queue, imgs)
+    }
+
+    /// Wraps a texture allocated elsewhere, e.g. an imported video surface. The
+    /// quad is sized to match the texture.
+    pub fn from_wgpu_texture(
+        device: &wgpu::Device,
+        texture: wgpu::Texture,
+        label: Option<&str>,
+    ) -> Result<Self> {
+        let size = texture.size();
+        let mut builder = TextureBuilder::new().size(size.width as f32, size.height as f32);
+        builder.label = label;
+        builder.wrap(device, texture)
+    }
+
+    /// A texture with nothing uploaded, for rendering into before sampling it
+    /// as a pattern. Pass `RENDER_ATTACHMENT` in `usage` for that.
+    pub fn empty(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        usage: wgpu::TextureUsages,
+        label: Option<&str>,
+    ) -> Result<Self> {
+        let mut builder = TextureBuilder::new()
+            .size(width as f32, height as f32)
+            .usage(usage);
+        builder.label = label;
+        builder.build_empty(device, width, height, format)
+    }
+
+    /// Decodes every frame of a GIF or APNG into one layer of a texture array,
+    /// returning the frame delays in layer order.
+    pub fn from_animated(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: Option<&str>,
+    ) -> Result<(Self, Vec<Duration>)> {
+        use image::AnimationDecoder;
+
+        let frames = match image::guess_format(bytes)? {
+            image::ImageFormat::Gif => {
+                image::codecs::gif::GifDecoder::new(Cursor::new(bytes))?.into_frames()
+            }
+            image::ImageFormat::Png => {
+                let decoder = image::codecs::png::PngDecoder::new(Cursor::new(bytes))?;
+                if !decoder.is_apng() {
+                    return Err(TextureError::Invalid("PNG image is not animated".into()));
+                }
+                decoder.apng().into_frames()
+            }
+            format => {
+                return Err(TextureError::Invalid(format!(
+                    "{:?} images cannot be animated",
+                    format
+                )))
+            }
+        };
+        let frames = frames.collect_frames()?;
+
+        let delays = frames.iter().map(|f| Duration::from(f.delay())).collect();
+        let imgs: Vec<_> = frames
+            .into_iter()
+            .map(|f| image::DynamicImage::ImageRgba8(f.into_buffer()))
+            .collect();
+        let texture = Self::from_images(device, queue, &imgs, label)?;
+        Ok((texture, delays))
+    }
+
+    pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+    pub fn create_depth_texture(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        label: Option<&str>,
+    ) -> Self {
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label,
-            size,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            format: Self::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
             view_formats: &[],
         });
 
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler_desc = wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 100.0,
+            ..Default::default()
+        };
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: sampler_label(label).as_deref(),
+            ..sampler_desc.clone()
+        });
+        let drop_log = DropLog::new(&texture);
+
+        Self {
+            texture: Arc::new(texture),
+            view: Arc::new(view),
+            sampler: Arc::new(sampler),
+            vertex_buffer: None,
+            index_buffer: None,
+            instance_buffer: None,
+            uniform_buffer: None,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sampler_desc,
+            transform_stride: None,
+            quad: quad(width as f32, height as f32).0,
+            content_size: (width, height),
+            has_alpha: false,
+            load_scale: 1.0,
+            builder: TextureBuilder::default(),
+            label: label.map(str::to_owned),
+            drop_log,
+        }
+    }
+
+    /// Swaps in a new sampler, keeping the filtering the texture was built
+    /// with. Bind groups made before this still hold the old sampler and need
+    /// recreating.
+    pub fn set_repetition(&mut self, device: &wgpu::Device, repetition: Repetition) -> Result<()> {
+        let required = repetition.required_features();
+        if !device.features().contains(required) {
+            return Err(TextureError::MissingFeatures(required));
+        }
+        let (address_mode_u, address_mode_v) = repetition.address_modes();
+        self.sampler_desc.address_mode_u = address_mode_u;
+        self.sampler_desc.address_mode_v = address_mode_v;
+        self.sampler_desc.border_color = repetition.border_color();
+        self.sampler = Arc::new(device.create_sampler(&wgpu::SamplerDescriptor {
+            label: sampler_label(self.label.as_deref()).as_deref(),
+            ..self.sampler_desc.clone()
+        }));
+        self.builder.repetition = repetition;
+        Ok(())
+    }
+
+    /// Another handle to the same GPU image with its own uniform buffer, so
+    /// the pattern can be drawn with a different transform without uploading
+    /// it twice. Bind groups are per handle.
+    pub fn share(&self, device: &wgpu::Device, transform: TextureTransform) -> Texture {
+        let uniform_buffer = self.uniform_buffer.as_ref().map(|buffer| {
+            let mut contents = bytemuck::bytes_of(&transform).to_vec();
+            if let Some(stride) = self.transform_stride {
+                contents.resize(stride as usize, 0);
+                contents = contents.repeat((buffer.size() / stride) as usize);
+            }
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(
+                    &self.resource_label("uniform buffer", "Texture transform uniform buffer"),
+                ),
+                contents: &contents,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            })
+        });
+        Texture {
+            texture: Arc::clone(&self.texture),
+            view: Arc::clone(&self.view),
+            sampler: Arc::clone(&self.sampler),
+            vertex_buffer: self.vertex_buffer.clone(),
+            index_buffer: self.index_buffer.clone(),
+            instance_buffer: self.instance_buffer.clone(),
+            uniform_buffer,
+            view_dimension: self.view_dimension,
+            sampler_desc: self.sampler_desc.clone(),
+            transform_stride: self.transform_stride,
+            quad: self.quad,
+            content_size: self.content_size,
+            has_alpha: self.has_alpha,
+            load_scale: self.load_scale,
+            builder: self.builder,
+            label: self.label.clone(),
+            drop_log: self.drop_log.clone(),
+        }
+    }
+
+    /// Where the quad's corners land once `transform.transform` is applied,
+    /// in the same pixel space the shader maps to clip space.
+    pub fn transformed_bounds(&self, transform: &TextureTransform) -> [[f32; 2]; 4] {
+        let m = transform.transform;
+        self.quad.map(|v| {
+            let [x, y, _] = v.position;
+            [
+                m[0][0] * x + m[1][0] * y + m[3][0],
+                m[0][1] * x + m[1][1] * y + m[3][1],
+            ]
+        })
+    }
+
+    /// Maps a point to the pattern uv drawn there, for picking. `point` is in
+    /// the same pixel space as `transformed_bounds`: the uniform's
+    /// `surface_size` with the origin at the top left and y pointing down. The result is in image
+    /// uv units before the sampler wraps it, so (1.5, 0.25) is a quarter of the
+    /// way down the second repeat; multiply the fractional part by the image
+    /// size for the texel. `None` if `transform.transform` is singular or the
+    /// quad has no area.
+    pub fn screen_to_uv(&self, transform: &TextureTransform, point: [f32; 2]) -> Option<[f32; 2]> {
+        let m = inverse(transform.transform)?;
+        let [px, py] = point;
+        let x = m[0][0] * px + m[1][0] * py + m[3][0];
+        let y = m[0][1] * px + m[1][1] * py + m[3][1];
+
+        // The quad is axis aligned in its own space, so interpolate the uv
+        // along the A-B and A-D edges.
+        let [a, b, _, d] = self.quad;
+        let (width, height) = (b.position[0] - a.position[0], d.position[1] - a.position[1]);
+        if width == 0.0 || height == 0.0 {
+            return None;
+        }
+        let s = (x - a.position[0]) / width;
+        let t = (y - a.position[1]) / height;
+        let u = a.tex_coords[0] + s * (b.tex_coords[0] - a.tex_coords[0]);
+        let v = a.tex_coords[1] + t * (d.tex_coords[1] - a.tex_coords[1]);
+
+        // From here on it's what the vertex shader does with the uv.
+        let [dim_x, dim_y, _, _] = transform.image_dimension;
+        let m = transform.inverse_bitmap_transform;
+        let (px, py) = (u * dim_x, v * dim_y);
+        let [offset_x, offset_y] = transform.pattern_offset;
+        Some([
+            (m[0][0] * px + m[1][0] * py + m[3][0]) / dim_x - offset_x,
+            (m[0][1] * px + m[1][1] * py + m[3][1]) / dim_y - offset_y,
+        ])
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        let size = self.texture.size();
+        (size.width, size.height)
+    }
+
+    /// The size of the image inside the texture. Smaller than `dimensions`
+    /// when `TextureBuilder::pad_to_pot` padded it out.
+    pub fn content_dimensions(&self) -> (u32, u32) {
+        self.content_size
+    }
+
+    /// Whether the source image carried an alpha channel, even though it was
+    /// expanded to RGBA for upload. Opaque textures can use a cheaper blend.
+    /// Raw pixel uploads can't tell and report it from the texture format.
+    pub fn has_alpha(&self) -> bool {
+        self.has_alpha
+    }
+
+    /// How much the source was shrunk by `TextureBuilder::max_dimension`, 1.0
+    /// if it fit. `dimensions` gives the size that was actually uploaded.
+    pub fn load_scale(&self) -> f32 {
+        self.load_scale
+    }
+
+    /// A fresh view of the underlying texture, e.g. for a single mip level or
+    /// array layer in a bind group of your own. `view` stays the default one.
+    pub fn create_view(&self, desc: &wgpu::TextureViewDescriptor) -> wgpu::TextureView {
+        self.texture.create_view(desc)
+    }
+
+    /// A view of `count` mips starting at `base`, for using one level as the
+    /// source of a downsampling pass.
+    pub fn view_of_mip(&self, base: u32, count: u32) -> wgpu::TextureView {
+        assert!(
+            count > 0 && base + count <= self.texture.mip_level_count(),
+            "mips {}..{} out of range for {} levels",
+            base,
+            base + count,
+            self.texture.mip_level_count()
+        );
+        self.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(&self.resource_label("mip view", "Texture mip view")),
+            dimension: Some(self.view_dimension),
+            base_mip_level: base,
+            mip_level_count: NonZeroU32::new(count),
+            ..Default::default()
+        })
+    }
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &layout_entries(wgpu::TextureViewDimension::D2, false, false, false),
+            label: Some("texture_bind_group_layout"),
+        })
+    }
+
+    /// Like `bind_group_layout`, but matching this texture's view dimension,
+    /// whether its transforms use dynamic offsets, whether its sampler
+    /// compares and whether it is multisampled.
+    pub fn bind_group_layout_for(&self, device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &layout_entries(
+                self.view_dimension,
+                self.transform_stride.is_some(),
+                self.sampler_desc.compare.is_some(),
+                self.texture.sample_count() > 1,
+            ),
+            label: Some("texture_bind_group_layout"),
+        })
+    }
+
+    /// The surface format to render patterns into: the first sRGB one the
+    /// surface offers, falling back to whatever it lists first.
+    pub fn preferred_format(
+        adapter: &wgpu::Adapter,
+        surface: &wgpu::Surface,
+    ) -> wgpu::TextureFormat {
+        let formats = surface.get_capabilities(adapter).formats;
+        formats
+            .iter()
+            .copied()
+            .find(|f| f.describe().srgb)
+            .unwrap_or(formats[0])
+    }
+
+    pub fn create_shader_module(device: &wgpu::Device) -> wgpu::ShaderModule {
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Pattern shader"),
+            source: wgpu::ShaderSource::Wgsl(PATTERN_SHADER.into()),
+        })
+    }
+
+    /// `layout` is the one the bind groups drawn with it were made from, e.g.
+    /// `bind_group_layout_for` for textures with `transform_slots`.
+    pub fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        blend: BlendMode,
+    ) -> wgpu::RenderPipeline {
+        Self::pattern_pipeline(
+            device,
+            layout,
+            format,
+            blend,
+            "vs_main",
+            "fs_main",
+            &[Vertex::desc()],
+        )
+    }
+
+    /// For textures built with `TextureBuilder::tile_grid`, drawn with
+    /// `draw_instanced`.
+    pub fn create_instanced_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        blend: BlendMode,
+    ) -> wgpu::RenderPipeline {
+        Self::pattern_pipeline(
+            device,
+            layout,
+            format,
+            blend,
+            "vs_main_instanced",
+            "fs_main",
+            &[Vertex::desc(), TileInstance::desc()],
+        )
+    }
+
+    /// For textures built with `TextureBuilder::vertex_colors`, whose vertex
+    /// buffer holds `VertexColored`.
+    pub fn create_colored_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        blend: BlendMode,
+    ) -> wgpu::RenderPipeline {
+        Self::pattern_pipeline(
+            device,
+            layout,
+            format,
+            blend,
+            "vs_main_colored",
+            "fs_main_colored",
+            &[VertexColored::desc()],
+        )
+    }
+
+    fn pattern_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        blend: BlendMode,
+        vs_entry_point: &str,
+        fs_entry_point: &str,
+        vertex_layouts: &[wgpu::VertexBufferLayout],
+    ) -> wgpu::RenderPipeline {
+        let shader = Self::create_shader_module(device);
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Pattern pipeline layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Pattern pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: vs_entry_point,
+                buffers: vertex_layouts,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: fs_entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(blend.blend_state()),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+
+    pub fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::BindGroup {
+        let [view, sampler, uniform] = self.binding_resources();
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: view,
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: sampler,
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform,
+                },
+            ],
+            label: Some(&self.resource_label("bind group", "texture_bind_group")),
+        })
+    }
+
+    /// The view, sampler and transform uniform that `bind_group` binds at 0,
+    /// 1 and 2, for placing at other indices in a layout of your own. The
+    /// uniform covers one transform, as dynamic offsets expect. Panics if the
+    /// texture was built without buffers.
+    pub fn binding_resources(&self) -> [wgpu::BindingResource<'_>; 3] {
+        [
+            wgpu::BindingResource::TextureView(&self.view),
+            wgpu::BindingResource::Sampler(&self.sampler),
+            wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer: self.uniform_buffer(),
+                offset: 0,
+                size: wgpu::BufferSize::new(std::mem::size_of::<TextureTransform>() as u64),
+            }),
+        ]
+    }
+
+    /// Replaces the image with `pixels` at the image's own size, padding them
+    /// out for `pad_to_pot` textures and regenerating mips if the texture has
+    /// them. The pixels are taken raw and must already be prepared the way the
+    /// texture was built, e.g. premultiplied for `premultiply_alpha(true)`;
+    /// `reload` and `write_subimage` apply the builder options themselves.
+    pub fn update_rgba8(
+        &self,
+        queue: &wgpu::Queue,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<()> {
+        self.ensure_rgba8()?;
+        if (width, height) != self.content_size {
+            return Err(TextureError::DimensionMismatch {
+                expected: self.content_size,
+                actual: (width, height),
+            });
+        }
+        check_rgba8_len(pixels, width, height)?;
+
+        let size = self.texture.size();
+        let mut pixels = Cow::Borrowed(pixels);
+        if (width, height) != (size.width, size.height) {
+            pixels = Cow::Owned(pad_rgba8(&pixels, width, size.width, size.height));
+        }
+        write_rgba8(queue, &self.texture, 0, &pixels, size.width, size.height);
+        Ok(())
+    }
+
+    /// Decodes `bytes` into this texture for hot reloading, with the options
+    /// it was built with. An image of the same size is uploaded in place.
+    /// Returns true if the size changed and the texture was rebuilt, in which
+    /// case bind groups need recreating. Texture arrays are rejected. The transforms in
+    /// the uniform buffer are kept, with `image_dimension` set to the new size.
+    pub fn reload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+    ) -> Result<bool> {
+        self.ensure_rgba8()?;
+        let size = self.texture.size();
+        if size.depth_or_array_layers > 1 {
+            return Err(TextureError::Invalid(
+                "texture arrays cannot be reloaded".into(),
+            ));
+        }
+        let img = image::load_from_memory(bytes)?;
+        let label = self.label.clone();
+        let builder = TextureBuilder {
+            label: label.as_deref(),
+            sampler: Some(&self.sampler),
+            ..self.builder
+        };
+        let resized = builder.fit_max_dimension(device, &img);
+        let rgba = builder.rgba8_pixels(&resized);
+        let (width, height) = rgba.dimensions();
+        let load_scale = match resized {
+            Cow::Owned(_) => width as f32 / img.width() as f32,
+            Cow::Borrowed(_) => 1.0,
+        };
+        if (width, height) == self.content_size {
+            let mut pixels = builder.prepare_rgba8(&rgba);
+            if (width, height) != (size.width, size.height) {
+                pixels = Cow::Owned(pad_rgba8(&pixels, width, size.width, size.height));
+            }
+            write_rgba8(queue, &self.texture, 0, &pixels, size.width, size.height);
+            self.has_alpha = img.color().has_alpha();
+            self.load_scale = load_scale;
+            return Ok(false);
+        }
+
+        let mut texture = builder.build_rgba8(device, queue, &rgba, width, height)?;
+        texture.has_alpha = img.color().has_alpha();
+        texture.load_scale = load_scale;
+        texture.sampler_desc = self.sampler_desc.clone();
+        if let (Some(_), Some(old)) = (&texture.uniform_buffer, self.uniform_buffer.take()) {
+            texture.uniform_buffer = Some(old);
+            let size = texture.texture.size();
+            texture.set_image_dimension(queue, size.width as f32, size.height as f32);
+        }
+        *self = texture;
+        Ok(true)
+    }
+
+    /// Writes `img` into the texture at `origin`, e.g. a sprite into an atlas,
+    /// premultiplied and tone mapped as the texture was built. Only the base
+    /// mip level is written; existing mips are left untouched.
+    pub fn write_subimage(
+        &self,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        origin: (u32, u32),
+    ) -> Result<()> {
+        self.ensure_rgba8()?;
+        let size = self.texture.size();
+        let (width, height) = (img.width(), img.height());
+        if origin.0 as u64 + width as u64 > size.width as u64
+            || origin.1 as u64 + height as u64 > size.height as u64
+        {
+            return Err(TextureError::OutOfBounds {
+                origin,
+                size: (width, height),
+                bounds: (size.width, size.height),
+            });
+        }
+
+        let rgba = self.builder.rgba8_pixels(img);
+        let pixels = self.builder.prepare_rgba8(&rgba);
+        write_level(
+            queue,
+            &self.texture,
+            0,
+            wgpu::Origin3d {
+                x: origin.0,
+                y: origin.1,
+                z: 0,
+            },
+            &pixels,
+            width,
+            height,
+        );
+        Ok(())
+    }
+
+    /// Copies the base level back to the CPU, blocking until the GPU is done.
+    /// Not available on wasm32, where the map callback only runs once control
+    /// returns to the browser, so waiting for it here would never finish.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_rgba8(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Vec<u8>> {
+        self.ensure_rgba8()?;
+        if !self.texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            return Err(TextureError::MissingUsage(wgpu::TextureUsages::COPY_SRC));
+        }
+
+        let size = self.texture.size();
+        let unpadded_bytes_per_row = 4 * size.width;
+        let padded_bytes_per_row = aligned_bytes_per_row(size.width, 4);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&self.resource_label("readback buffer", "Texture readback buffer")),
+            size: padded_bytes_per_row as u64 * size.height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some(&self.resource_label("readback encoder", "Texture readback encoder")),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: NonZeroU32::new(size.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|_| TextureError::Map(wgpu::BufferAsyncError))?
+            .map_err(TextureError::Map)?;
+
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * size.height) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+            }
+        }
+        buffer.unmap();
+        Ok(pixels)
+    }
+
+    fn resource_label(&self, resource: &str, fallback: &str) -> String {
+        resource_label(self.label.as_deref(), resource, fallback)
+    }
+
+    fn ensure_rgba8(&self) -> Result<()> {
+        let format = self.texture.format();
+        if !matches!(
+            format,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb
+        ) {
+            return Err(TextureError::UnsupportedFormat(format));
+        }
+        if self.texture.sample_count() > 1 {
+            return Err(TextureError::Multisampled);
+        }
+        Ok(())
+    }
+
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, bind_group: &'a wgpu::BindGroup) {
+        self.draw_at(pass, bind_group, 0);
+    }
+
+    /// Draws with the transform in slot `index`. Only textures built with
+    /// `TextureBuilder::transform_slots` have more than slot 0.
+    pub fn draw_at<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        bind_group: &'a wgpu::BindGroup,
+        index: u32,
+    ) {
+        self.bind_quad(pass, bind_group, index);
+        pass.draw_indexed(0..6, 0, 0..1);
+    }
+
+    /// Draws the first `count` tiles of the grid set up with
+    /// `TextureBuilder::tile_grid`, using `create_instanced_pipeline`.
+    pub fn draw_instanced<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        bind_group: &'a wgpu::BindGroup,
+        count: u32,
+    ) {
+        let instance_buffer = self
+            .instance_buffer
+            .as_ref()
+            .expect("texture was created without a tile grid");
+        let tiles = instance_buffer.size() / std::mem::size_of::<TileInstance>() as u64;
+        assert!(count as u64 <= tiles, "texture only has {} tiles", tiles);
+        self.bind_quad(pass, bind_group, 0);
+        pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        pass.draw_indexed(0..6, 0, 0..count);
+    }
+
+    fn bind_quad<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        bind_group: &'a wgpu::BindGroup,
+        index: u32,
+    ) {
+        let vertex_buffer = self
+            .vertex_buffer
+            .as_ref()
+            .expect("texture was created without a vertex buffer");
+        let index_buffer = self
+            .index_buffer
+            .as_ref()
+            .expect("texture was created without an index buffer");
+        match self.transform_stride {
+            Some(stride) => {
+                pass.set_bind_group(0, bind_group, &[(stride * index as u64) as u32]);
+            }
+            None => {
+                assert_eq!(index, 0, "texture has a single transform slot");
+                pass.set_bind_group(0, bind_group, &[]);
+            }
+        }
+        pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+    }
+
+    fn uniform_buffer(&self) -> &wgpu::Buffer {
+        self.uniform_buffer
+            .as_ref()
+            .expect("texture was created without a uniform buffer")
+    }
+
+    pub fn update_transform(&self, queue: &wgpu::Queue, transform: TextureTransform) {
+        self.update_transform_at(queue, 0, transform);
+    }
+
+    pub fn update_transform_at(
+        &self,
+        queue: &wgpu::Queue,
+        index: u32,
+        transform: TextureTransform,
+    ) {
+        let size = std::mem::size_of::<TextureTransform>() as wgpu::BufferAddress;
+        let offset = self.transform_stride.unwrap_or(size) * index as u64;
+        assert!(
+            offset + size <= self.uniform_buffer().size(),
+            "transform slot {} is out of range",
+            index
+        );
+        queue.write_buffer(
+            self.uniform_buffer(),
+            offset,
+            bytemuck::cast_slice(&[transform]),
+        );
+    }
+
+    /// Rewrites only `image_dimension`, in every transform slot, e.g. after
+    /// `reload` changed the image size.
+    pub fn set_image_dimension(&self, queue: &wgpu::Queue, width: f32, height: f32) {
+        let field = std::mem::offset_of!(TextureTransform, image_dimension);
+        self.write_uniform_field(queue, field, &[width, height, 0.0, 1.0]);
+    }
+
+    /// Rewrites only `surface_size`, in every transform slot, for when the
+    /// target is resized.
+    pub fn set_surface_size(&self, queue: &wgpu::Queue, width: f32, height: f32) {
+        let field = std::mem::offset_of!(TextureTransform, surface_size);
+        self.write_uniform_field(queue, field, &[width, height]);
+    }
+
+    fn write_uniform_field(&self, queue: &wgpu::Queue, field: usize, value: &[f32]) {
+        let buffer = self.uniform_buffer();
+        let stride = self
+            .transform_stride
+            .unwrap_or(std::mem::size_of::<TextureTransform>() as wgpu::BufferAddress);
+        for slot in 0..buffer.size() / stride {
+            queue.write_buffer(
+                buffer,
+                slot * stride + field as wgpu::BufferAddress,
+                bytemuck::cast_slice(value),
+            );
+        }
+    }
+}
+
+/// Logs each GPU texture as its last handle goes away, to line drops up with
+/// VRAM traces. The size is an estimate from the format, mips and layers.
+/// Every handle `share` makes holds a clone, so the log fires once. Kept as a
+/// field rather than `Drop for Texture`, which would stop callers moving
+/// fields out of a `Texture` only when the feature is on.
+#[cfg(feature = "drop-log")]
+#[derive(Clone)]
+struct DropLog {
+    _info: Arc<DropInfo>,
+}
+
+#[cfg(feature = "drop-log")]
+struct DropInfo {
+    size: wgpu::Extent3d,
+    format: wgpu::TextureFormat,
+    bytes: u64,
+}
+
+#[cfg(feature = "drop-log")]
+impl DropLog {
+    fn new(texture: &wgpu::Texture) -> Self {
+        let size = texture.size();
+        let info = texture.format().describe();
+        let (block_width, block_height) = info.block_dimensions;
+        let bytes: u64 = (0..texture.mip_level_count())
+            .map(|level| {
+                let width = (size.width >> level).max(1).div_ceil(block_width as u32);
+                let height = (size.height >> level).max(1).div_ceil(block_height as u32);
+                width as u64 * height as u64 * info.block_size as u64
+            })
+            .sum::<u64>()
+            * size.depth_or_array_layers as u64
+            * texture.sample_count() as u64;
+        DropLog {
+            _info: Arc::new(DropInfo {
+                size,
+                format: texture.format(),
+                bytes,
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "drop-log")]
+impl Drop for DropInfo {
+    fn drop(&mut self) {
+        log::debug!(
+            "dropping {}x{}x{} {:?} texture, ~{} bytes",
+            self.size.width,
+            self.size.height,
+            self.size.depth_or_array_layers,
+            self.format,
+            self.bytes
+        );
+    }
+}
+
+#[cfg(not(feature = "drop-log"))]
+#[derive(Clone)]
+struct DropLog;
+
+#[cfg(not(feature = "drop-log"))]
+impl DropLog {
+    fn new(_texture: &wgpu::Texture) -> Self {
+        DropLog
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TextureBuilder<'a> {
+    label: Option<&'a str>,
+    size: Option<(f32, f32)>,
+    repetition: Repetition,
+    address_mode_w: wgpu::AddressMode,
+    srgb: bool,
+    mipmaps: bool,
+    mag_filter: wgpu::FilterMode,
+    min_filter: wgpu::FilterMode,
+    mipmap_filter: wgpu::FilterMode,
+    anisotropy: u8,
+    transform: Option<TextureTransform>,
+    winding: Winding,
+    anchor: Anchor,
+    flip_x: bool,
+    flip_y: bool,
+    half_texel_inset: bool,
+    vertex_colors: Option<[[f32; 4]; 4]>,
+    buffers: bool,
+    premultiply_alpha: bool,
+    usage: wgpu::TextureUsages,
+    native_format: bool,
+    sample_count: u32,
+    transform_slots: u32,
+    color_transform: ColorTransform,
+    color_adjust: Option<[f32; 4]>,
+    surface_size: Option<[f32; 2]>,
+    tile_grid: Option<(u32, u32)>,
+    max_dimension: Option<u32>,
+    compare: Option<wgpu::CompareFunction>,
+    bitmap_transform: Option<[[f32; 4]; 4]>,
+    pad_to_pot: bool,
+    resize_filter: ResizeFilter,
+    fit: Fit,
+    lod_clamp: (f32, f32),
+    sampler: Option<&'a Arc<wgpu::Sampler>>,
+}
+
+impl<'a> Default for TextureBuilder<'a> {
+    fn default() -> Self {
+        Self {
+            label: None,
+            size: None,
+            repetition: Repetition::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            srgb: true,
+            mipmaps: false,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            anisotropy: 1,
+            transform: None,
+            winding: Winding::default(),
+            anchor: Anchor::default(),
+            flip_x: false,
+            flip_y: false,
+            half_texel_inset: false,
+            vertex_colors: None,
+            buffers: true,
+            premultiply_alpha: false,
+            usage: wgpu::TextureUsages::empty(),
+            native_format: false,
+            sample_count: 1,
+            transform_slots: 0,
+            color_transform: ColorTransform::None,
+            color_adjust: None,
+            surface_size: None,
+            tile_grid: None,
+            max_dimension: None,
+            compare: None,
+            bitmap_transform: None,
+            pad_to_pot: false,
+            resize_filter: ResizeFilter::default(),
+            fit: Fit::default(),
+            lod_clamp: (0.0, f32::MAX),
+            sampler: None,
+        }
+    }
+}
+
+impl<'a> TextureBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn label(mut self, label: &'a str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// The quad's size in pixels. Without one the quad matches the image,
+    /// so it keeps its aspect ratio.
+    pub fn size(mut self, width: f32, height: f32) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    pub fn repetition(mut self, repetition: Repetition) -> Self {
+        self.repetition = repetition;
+        self
+    }
+
+    /// Only matters for array or 3D views; 2D sampling ignores the W axis.
+    pub fn address_mode_w(mut self, address_mode_w: wgpu::AddressMode) -> Self {
+        self.address_mode_w = address_mode_w;
+        self
+    }
+
+    pub fn srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+
+    pub fn mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    pub fn filter(mut self, filter: wgpu::FilterMode) -> Self {
+        self.mag_filter = filter;
+        self.min_filter = filter;
+        self.mipmap_filter = filter;
+        self
+    }
+
+    pub fn mag_filter(mut self, filter: wgpu::FilterMode) -> Self {
+        self.mag_filter = filter;
+        self
+    }
+
+    pub fn min_filter(mut self, filter: wgpu::FilterMode) -> Self {
+        self.min_filter = filter;
+        self
+    }
+
+    pub fn mipmap_filter(mut self, filter: wgpu::FilterMode) -> Self {
+        self.mipmap_filter = filter;
+        self
+    }
+
+    pub fn anisotropy(mut self, anisotropy: u8) -> Self {
+        self.anisotropy = anisotropy;
+        self
+    }
+
+    /// Uses `sampler` instead of creating one, for many textures that sample
+    /// the same way. The filter, repetition and compare settings should still
+    /// match it; `bind_group_layout_for` and `set_repetition` go by them.
+    pub fn sampler(mut self, sampler: &'a Arc<wgpu::Sampler>) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    /// Limits sampling to mips `min..=max`, fractional levels included. The
+    /// default leaves every level available.
+    pub fn lod_clamp(mut self, min: f32, max: f32) -> Self {
+        self.lod_clamp = (min, max);
+        self
+    }
+
+    pub fn transform(mut self, transform: TextureTransform) -> Self {
+        self.transform = Some(transform);
+        self
+    }
+
+    /// Overrides the uniform's `bitmap_transform` and recomputes its inverse,
+    /// on top of `transform` if that was set too.
+    pub fn bitmap_transform(mut self, bitmap_transform: [[f32; 4]; 4]) -> Self {
+        self.bitmap_transform = Some(bitmap_transform);
+        self
+    }
+
+    pub fn winding(mut self, winding: Winding) -> Self {
+        self.winding = winding;
+        self
+    }
+
+    pub fn anchor(mut self, anchor: Anchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    pub fn fit(mut self, fit: Fit) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    pub fn flip_x(mut self, flip_x: bool) -> Self {
+        self.flip_x = flip_x;
+        self
+    }
+
+    /// Useful for sources whose origin is the bottom-left corner.
+    pub fn flip_y(mut self, flip_y: bool) -> Self {
+        self.flip_y = flip_y;
+        self
+    }
+
+    /// Pulls the quad's uv in by half a texel on each side so linear filtering
+    /// at the edges of an atlas region doesn't pick up its neighbours.
+    pub fn half_texel_inset(mut self, half_texel_inset: bool) -> Self {
+        self.half_texel_inset = half_texel_inset;
+        self
+    }
+
+    /// Tints for the quad's corners, top-left first and then clockwise. The
+    /// vertex buffer then holds `VertexColored`, so draw with
+    /// `Texture::create_colored_pipeline`.
+    pub fn vertex_colors(mut self, colors: [[f32; 4]; 4]) -> Self {
+        self.vertex_colors = Some(colors);
+        self
+    }
+
+    /// Allocates RGBA8 uploads at the next power of two in each direction,
+    /// with the image in the top-left corner and the quad's uvs shrunk to
+    /// match. Sampler repeat then tiles the padding too, so pair this with
+    /// `NoRepeat` or `tile_grid`. See `Texture::content_dimensions`.
+    pub fn pad_to_pot(mut self, pad_to_pot: bool) -> Self {
+        self.pad_to_pot = pad_to_pot;
+        self
+    }
+
+    /// Adds an instance buffer laying `columns` x `rows` copies of the quad
+    /// side by side, for a finite tiled area instead of sampler repeat.
+    pub fn tile_grid(mut self, columns: u32, rows: u32) -> Self {
+        self.tile_grid = Some((columns, rows));
+        self
+    }
+
+    /// Images wider or taller than this, or than the device's texture limit,
+    /// are shrunk with `resize_filter` in `build` instead of failing the
+    /// upload. The aspect ratio is kept; see `Texture::load_scale`.
+    pub fn max_dimension(mut self, max_dimension: u32) -> Self {
+        self.max_dimension = Some(max_dimension);
+        self
+    }
+
+    pub fn resize_filter(mut self, resize_filter: ResizeFilter) -> Self {
+        self.resize_filter = resize_filter;
+        self
+    }
+
+    /// Makes the sampler a comparison sampler for PCF-style depth or mask
+    /// lookups. Only valid on depth textures, e.g. from `build_empty` with
+    /// `Texture::DEPTH_FORMAT`, and the built-in pattern shader doesn't bind
+    /// one; use `bind_group_layout_for` with your own shader.
+    pub fn compare(mut self, compare: wgpu::CompareFunction) -> Self {
+        self.compare = Some(compare);
+        self
+    }
+
+    pub fn buffers(mut self, buffers: bool) -> Self {
+        self.buffers = buffers;
+        self
+    }
+
+    /// Source pixels are treated as straight alpha and uploaded unchanged
+    /// unless this is enabled, in which case RGB is multiplied by alpha
+    /// before upload. For sRGB textures the multiply happens in linear space.
+    pub fn premultiply_alpha(mut self, premultiply_alpha: bool) -> Self {
+        self.premultiply_alpha = premultiply_alpha;
+        self
+    }
+
+    /// Extra usages ORed into the TEXTURE_BINDING | COPY_DST the upload needs.
+    pub fn usage(mut self, usage: wgpu::TextureUsages) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// Upload Luma8, Luma16 and Rgba16 images as R8Unorm, R16Unorm and
+    /// Rgba16Unorm instead of expanding them to RGBA8. The 16-bit formats need
+    /// `Features::TEXTURE_FORMAT_16BIT_NORM`.
+    pub fn native_format(mut self, native_format: bool) -> Self {
+        self.native_format = native_format;
+        self
+    }
+
+    /// Multisampled textures can only be made with `build_empty` and need
+    /// `RENDER_ATTACHMENT` usage. Render into them and resolve into a single
+    /// sampled texture to use the result as a pattern.
+    pub fn sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    /// Sizes the uniform buffer for `slots` transforms bound with a dynamic
+    /// offset, so one bind group can draw the pattern many times in a pass.
+    /// Pair with `update_transform_at`, `draw_at` and `bind_group_layout_for`.
+    pub fn transform_slots(mut self, slots: u32) -> Self {
+        self.transform_slots = slots;
+        self
+    }
+
+    /// Treats the source as linear and tone maps it during the 8-bit
+    /// conversion in `build`.
+    pub fn color_transform(mut self, color_transform: ColorTransform) -> Self {
+        self.color_transform = color_transform;
+        self
+    }
+
+    /// Overrides the uniform's `color_adjust`, which is otherwise identity or
+    /// whatever the `transform` passed in carries.
+    pub fn color_adjust(mut self, brightness: f32, contrast: f32, gamma: f32) -> Self {
+        self.color_adjust = Some([brightness, contrast, gamma, 0.0]);
+        self
+    }
+
+    /// The size of the target the quad is drawn into, `DEFAULT_SURFACE_SIZE`
+    /// unless the `transform` passed in says otherwise. Keep it in step with
+    /// resizes through `Texture::set_surface_size`.
+    pub fn surface_size(mut self, width: f32, height: f32) -> Self {
+        self.surface_size = Some([width, height]);
+        self
+    }
+
+    /// Uploads go through `queue.write_texture`, which copies the pixels into
+    /// wgpu's staging memory straight away and defers the GPU copies to the
+    /// next `queue.submit`. Building many textures in a loop therefore lands
+    /// in a single submission without any extra batching on our side.
+    pub fn build(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+    ) -> Result<Texture> {
+        let resized = self.fit_max_dimension(device, img);
+        let mut texture = self.build_image(device, queue, &resized)?;
+        texture.has_alpha = img.color().has_alpha();
+        if let Cow::Owned(resized) = &resized {
+            texture.load_scale = resized.width() as f32 / img.width() as f32;
+        }
+        Ok(texture)
+    }
+
+    /// `img` shrunk to `max_dimension`, if it is set and the image is larger.
+    fn fit_max_dimension<'i>(
+        &self,
+        device: &wgpu::Device,
+        img: &'i image::DynamicImage,
+    ) -> Cow<'i, image::DynamicImage> {
+        if let Some(max_dimension) = self.max_dimension {
+            let max = max_dimension.min(device.limits().max_texture_dimension_2d);
+            if img.width() > max || img.height() > max {
+                return Cow::Owned(img.resize(max, max, self.resize_filter.filter_type()));
+            }
+        }
+        Cow::Borrowed(img)
+    }
+
+    /// The RGBA8 pixels for `img`, tone mapped if `color_transform` is set.
+    fn rgba8_pixels(&self, img: &image::DynamicImage) -> image::RgbaImage {
+        if self.color_transform != ColorTransform::None {
+            tone_map_rgba8(img, self.color_transform, self.srgb)
+        } else {
+            img.to_rgba8()
+        }
+    }
+
+    fn build_image(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+    ) -> Result<Texture> {
+        if self.native_format {
+            self.validate_upload()?;
+            match img {
+                image::DynamicImage::ImageLuma8(luma) => {
+                    return self.build_native(
+                        device,
+                        queue,
+                        luma.clone(),
+                        wgpu::TextureFormat::R8Unorm,
+                    );
+                }
+                image::DynamicImage::ImageLuma16(luma) => {
+                    return self.build_native(
+                        device,
+                        queue,
+                        luma.clone(),
+                        wgpu::TextureFormat::R16Unorm,
+                    );
+                }
+                image::DynamicImage::ImageRgba16(rgba) => {
+                    return self.build_native(
+                        device,
+                        queue,
+                        rgba.clone(),
+                        wgpu::TextureFormat::Rgba16Unorm,
+                    );
+                }
+                _ => {}
+            }
+        }
+        let rgba = self.rgba8_pixels(img);
+        self.build_rgba8(device, queue, &rgba, rgba.width(), rgba.height())
+    }
+
+    pub fn build_rgba8(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Texture> {
+        self.upload_rgba8(
+            device,
+            pixels,
+            width,
+            height,
+            false,
+            |texture, pixels, width, height| write_rgba8(queue, texture, 0, pixels, width, height),
+        )
+    }
+
+    /// BGRA8 pixels go into a `Bgra8Unorm(Srgb)` texture as they are; the
+    /// sampler hands the shader RGBA either way, so nothing is swizzled on
+    /// the CPU.
+    pub fn build_bgra8(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Texture> {
+        self.upload_rgba8(
+            device,
+            pixels,
+            width,
+            height,
+            true,
+            |texture, pixels, width, height| write_rgba8(queue, texture, 0, pixels, width, height),
+        )
+    }
+
+    /// Like `build`, but the texels are staged in buffers and copied on
+    /// `encoder` instead of being written through the queue. The image is
+    /// always converted to RGBA8, so `native_format` and `max_dimension` don't
+    /// apply. A `StagingBelt` can only target buffers, hence the plain
+    /// staging buffers.
+    pub fn build_with_encoder(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        img: &image::DynamicImage,
+    ) -> Result<Texture> {
+        let rgba = self.rgba8_pixels(img);
+        let (width, height) = rgba.dimensions();
+        let mut texture = self.upload_rgba8(
+            device,
+            &rgba,
+            width,
+            height,
+            false,
+            |texture, pixels, width, height| {
+                let staging_label = self.resource_label("staging buffer", "Texture staging buffer");
+                copy_rgba8(
+                    device,
+                    encoder,
+                    &staging_label,
+                    texture,
+                    pixels,
+                    width,
+                    height,
+                )
+            },
+        )?;
+        texture.has_alpha = img.color().has_alpha();
+        Ok(texture)
+    }
+
+    /// The shared half of the RGBA8 and BGRA8 builds; `write` puts the
+    /// prepared pixels and their mips into the fresh texture.
+    fn upload_rgba8(
+        &self,
+        device: &wgpu::Device,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        bgra: bool,
+        write: impl FnOnce(&wgpu::Texture, &[u8], u32, u32),
+    ) -> Result<Texture> {
+        self.validate_upload()?;
+        check_rgba8_len(pixels, width, height)?;
+        let mut pixels = self.prepare_rgba8(pixels);
+        let (content_width, content_height) = (width, height);
+        let (width, height) = if self.pad_to_pot && width > 0 && height > 0 {
+            (width.next_power_of_two(), height.next_power_of_two())
+        } else {
+            (width, height)
+        };
+        if (width, height) != (content_width, content_height) {
+            pixels = Cow::Owned(pad_rgba8(&pixels, content_width, width, height));
+        }
+
+        let format = match (bgra, self.srgb) {
+            (false, true) => wgpu::TextureFormat::Rgba8UnormSrgb,
+            (false, false) => wgpu::TextureFormat::Rgba8Unorm,
+            (true, true) => wgpu::TextureFormat::Bgra8UnormSrgb,
+            (true, false) => wgpu::TextureFormat::Bgra8Unorm,
+        };
+        let mip_level_count = if self.mipmaps {
+            mip_level_count(width, height)
+        } else {
+            1
+        };
+        let texture = self.create_texture(device, width, height, 1, format, mip_level_count)?;
+
+        write(&texture, &pixels, width, height);
+
+        self.finish_padded(
+            device,
+            texture,
+            wgpu::TextureViewDimension::D2,
+            (content_width, content_height),
+        )
+    }
+
+    /// Like `build_rgba8` for rows that are `stride` bytes apart. The rows are
+    /// handed to wgpu as they are unless mips or premultiplication need a
+    /// tightly packed copy anyway.
+    pub fn build_rgba8_strided(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        stride: u32,
+    ) -> Result<Texture> {
+        let row_len = width as usize * 4;
+        if (stride as usize) < row_len {
+            return Err(TextureError::Invalid(format!(
+                "stride of {} bytes is shorter than a {} pixel row",
+                stride, width
+            )));
+        }
+        if stride as usize == row_len {
+            return self.build_rgba8(device, queue, pixels, width, height);
+        }
+        let expected = stride as usize * height.saturating_sub(1) as usize + row_len;
+        if height > 0 && pixels.len() < expected {
+            return Err(TextureError::InvalidLength {
+                expected,
+                actual: pixels.len(),
+            });
+        }
+        if self.mipmaps || self.premultiply_alpha || self.pad_to_pot {
+            let mut packed = Vec::with_capacity(row_len * height as usize);
+            for row in pixels.chunks(stride as usize).take(height as usize) {
+                packed.extend_from_slice(&row[..row_len]);
+            }
+            return self.build_rgba8(device, queue, &packed, width, height);
+        }
+
+        self.validate_upload()?;
+        let format = if self.srgb {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        };
+        let texture = self.create_texture(device, width, height, 1, format, 1)?;
         queue.write_texture(
             wgpu::ImageCopyTexture {
                 aspect: wgpu::TextureAspect::All,
@@ -154,100 +2897,1259 @@ impl Texture {
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
             },
-            &rgba,
+            pixels,
             wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: NonZeroU32::new(4 * dimensions.0),
-                rows_per_image: NonZeroU32::new(dimensions.1),
+                bytes_per_row: NonZeroU32::new(stride),
+                rows_per_image: NonZeroU32::new(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
             },
-            size,
         );
 
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::Repeat,
-            address_mode_v: wgpu::AddressMode::Repeat,
-            address_mode_w: wgpu::AddressMode::Repeat,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Linear,
+        self.finish(device, texture, wgpu::TextureViewDimension::D2)
+    }
+
+    pub fn build_array(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        imgs: &[image::DynamicImage],
+    ) -> Result<Texture> {
+        self.validate_upload()?;
+        let first = imgs.first().ok_or(TextureError::Empty {
+            width: 0,
+            height: 0,
+            layers: 0,
+        })?;
+        let (width, height) = (first.width(), first.height());
+        for img in imgs {
+            if (img.width(), img.height()) != (width, height) {
+                return Err(TextureError::DimensionMismatch {
+                    expected: (width, height),
+                    actual: (img.width(), img.height()),
+                });
+            }
+        }
+
+        let format = if self.srgb {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        };
+        let mip_level_count = if self.mipmaps {
+            mip_level_count(width, height)
+        } else {
+            1
+        };
+        let texture = self.create_texture(
+            device,
+            width,
+            height,
+            imgs.len() as u32,
+            format,
+            mip_level_count,
+        )?;
+
+        for (layer, img) in imgs.iter().enumerate() {
+            let rgba = img.to_rgba8();
+            let rgba = self.prepare_rgba8(&rgba);
+            write_rgba8(queue, &texture, layer as u32, &rgba, width, height);
+        }
+
+        let mut texture = self.finish(device, texture, wgpu::TextureViewDimension::D2Array)?;
+        texture.has_alpha = imgs.iter().any(|img| img.color().has_alpha());
+        Ok(texture)
+    }
+
+    pub fn build_hdr(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+    ) -> Result<Texture> {
+        self.validate_upload()?;
+        let mut base = img.to_rgba32f();
+        if self.premultiply_alpha {
+            for p in base.pixels_mut() {
+                let a = p[3];
+                p[0] *= a;
+                p[1] *= a;
+                p[2] *= a;
+            }
+        }
+        let (width, height) = base.dimensions();
+        let levels = self.resized_levels(base);
+
+        let texture = self.create_texture(
+            device,
+            width,
+            height,
+            1,
+            wgpu::TextureFormat::Rgba16Float,
+            levels.len() as u32,
+        )?;
+        for (mip_level, level) in levels.iter().enumerate() {
+            let halfs: Vec<u16> = level
+                .as_raw()
+                .iter()
+                .map(|&c| half::f16::from_f32(c).to_bits())
+                .collect();
+            write_level(
+                queue,
+                &texture,
+                mip_level as u32,
+                wgpu::Origin3d::ZERO,
+                bytemuck::cast_slice(&halfs),
+                level.width(),
+                level.height(),
+            );
+        }
+
+        let mut texture = self.finish(device, texture, wgpu::TextureViewDimension::D2)?;
+        texture.has_alpha = img.color().has_alpha();
+        Ok(texture)
+    }
+
+    pub fn wrap(&self, device: &wgpu::Device, texture: wgpu::Texture) -> Result<Texture> {
+        self.validate()?;
+        let view_dimension = if texture.depth_or_array_layers() > 1 {
+            wgpu::TextureViewDimension::D2Array
+        } else {
+            wgpu::TextureViewDimension::D2
+        };
+        self.finish(device, texture, view_dimension)
+    }
+
+    pub fn build_empty(
+        &self,
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Result<Texture> {
+        self.validate()?;
+        let texture = self.create_texture(device, width, height, 1, format, 1)?;
+        self.finish(device, texture, wgpu::TextureViewDimension::D2)
+    }
+
+    /// Uploads pre-compressed block data such as BC1 or BC7 without decoding
+    /// it. `levels` holds the base level followed by any mips, each tightly
+    /// packed in block rows.
+    pub fn build_compressed(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        levels: &[&[u8]],
+    ) -> Result<Texture> {
+        let info = format.describe();
+        let (block_width, block_height) = (
+            info.block_dimensions.0 as u32,
+            info.block_dimensions.1 as u32,
+        );
+        if (block_width, block_height) == (1, 1) {
+            return Err(TextureError::UnsupportedFormat(format));
+        }
+        let levels: Vec<_> = levels
+            .iter()
+            .enumerate()
+            .map(|(mip_level, &data)| {
+                (
+                    data,
+                    (width >> mip_level).max(1),
+                    (height >> mip_level).max(1),
+                )
+            })
+            .collect();
+        self.build_mip_levels(device, queue, format, &levels)
+    }
+
+    /// Uploads a base level and its pre-generated mips as they are, each
+    /// given as tightly packed data with its width and height. Sizes have to
+    /// halve from one level to the next, rounding down to at least 1, and
+    /// `mipmaps` is ignored since nothing is generated. For block compressed
+    /// formats the base size must be a whole number of blocks.
+    pub fn build_mip_levels(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        levels: &[(&[u8], u32, u32)],
+    ) -> Result<Texture> {
+        self.validate_upload()?;
+        let info = format.describe();
+        if !device.features().contains(info.required_features) {
+            return Err(TextureError::MissingFeatures(info.required_features));
+        }
+        let &(_, width, height) = levels.first().ok_or_else(|| {
+            TextureError::Invalid("expected at least one mip level, got none".into())
+        })?;
+        let max_levels = mip_level_count(width, height).max(1);
+        if levels.len() as u32 > max_levels {
+            return Err(TextureError::Invalid(format!(
+                "expected between 1 and {} mip levels, got {}",
+                max_levels,
+                levels.len()
+            )));
+        }
+
+        let (block_width, block_height) = (
+            info.block_dimensions.0 as u32,
+            info.block_dimensions.1 as u32,
+        );
+        if !width.is_multiple_of(block_width) || !height.is_multiple_of(block_height) {
+            return Err(TextureError::Invalid(format!(
+                "{}x{} is not a multiple of the {}x{} block size",
+                width, height, block_width, block_height
+            )));
+        }
+
+        let texture = self.create_texture(device, width, height, 1, format, levels.len() as u32)?;
+        for (mip_level, &(data, level_width, level_height)) in levels.iter().enumerate() {
+            let expected_size = ((width >> mip_level).max(1), (height >> mip_level).max(1));
+            if (level_width, level_height) != expected_size {
+                return Err(TextureError::DimensionMismatch {
+                    expected: expected_size,
+                    actual: (level_width, level_height),
+                });
+            }
+            // Mips smaller than a block still occupy a whole one.
+            let level_width = level_width.div_ceil(block_width) * block_width;
+            let level_height = level_height.div_ceil(block_height) * block_height;
+            let expected = (level_width / block_width * level_height / block_height) as usize
+                * info.block_size as usize;
+            if data.len() != expected {
+                return Err(TextureError::InvalidLength {
+                    expected,
+                    actual: data.len(),
+                });
+            }
+            write_level(
+                queue,
+                &texture,
+                mip_level as u32,
+                wgpu::Origin3d::ZERO,
+                data,
+                level_width,
+                level_height,
+            );
+        }
+
+        self.finish(device, texture, wgpu::TextureViewDimension::D2)
+    }
+
+    /// Uploads an image in its own pixel format, for formats without the
+    /// dedicated RGBA8 path.
+    fn build_native<P>(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        base: image::ImageBuffer<P, Vec<P::Subpixel>>,
+        format: wgpu::TextureFormat,
+    ) -> Result<Texture>
+    where
+        P: image::Pixel + 'static,
+        P::Subpixel: bytemuck::Pod + 'static,
+    {
+        let required = format.describe().required_features;
+        if !device.features().contains(required) {
+            return Err(TextureError::MissingFeatures(required));
+        }
+
+        let (width, height) = base.dimensions();
+        let levels = self.resized_levels(base);
+        let texture = self.create_texture(device, width, height, 1, format, levels.len() as u32)?;
+        for (mip_level, level) in levels.iter().enumerate() {
+            write_level(
+                queue,
+                &texture,
+                mip_level as u32,
+                wgpu::Origin3d::ZERO,
+                bytemuck::cast_slice(level.as_raw()),
+                level.width(),
+                level.height(),
+            );
+        }
+
+        self.finish(device, texture, wgpu::TextureViewDimension::D2)
+    }
+
+    /// Builds the mip chain with the image crate's resampler, for pixel types
+    /// the RGBA8 box filter doesn't handle.
+    fn resized_levels<P>(
+        &self,
+        base: image::ImageBuffer<P, Vec<P::Subpixel>>,
+    ) -> Vec<image::ImageBuffer<P, Vec<P::Subpixel>>>
+    where
+        P: image::Pixel + 'static,
+        P::Subpixel: 'static,
+    {
+        let (width, height) = base.dimensions();
+        let mut levels = vec![base];
+        if self.mipmaps {
+            for _ in 1..mip_level_count(width, height) {
+                let prev = levels.last().unwrap();
+                let next = image::imageops::resize(
+                    prev,
+                    (prev.width() / 2).max(1),
+                    (prev.height() / 2).max(1),
+                    image::imageops::FilterType::Triangle,
+                );
+                levels.push(next);
+            }
+        }
+        levels
+    }
+
+    fn prepare_rgba8<'p>(&self, pixels: &'p [u8]) -> Cow<'p, [u8]> {
+        if !self.premultiply_alpha {
+            return Cow::Borrowed(pixels);
+        }
+        let mut pixels = pixels.to_vec();
+        if self.srgb {
+            premultiply_srgb8(&mut pixels);
+        } else {
+            premultiply_rgba8(&mut pixels);
+        }
+        Cow::Owned(pixels)
+    }
+
+    fn validate_upload(&self) -> Result<()> {
+        self.validate()?;
+        if self.sample_count > 1 {
+            return Err(TextureError::Multisampled);
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !matches!(self.sample_count, 1 | 2 | 4 | 8) {
+            return Err(TextureError::InvalidOption {
+                option: "sample_count",
+                reason: format!("must be 1, 2, 4 or 8, got {}", self.sample_count),
+            });
+        }
+        if !matches!(self.anisotropy, 1 | 2 | 4 | 8 | 16) {
+            return Err(TextureError::InvalidOption {
+                option: "anisotropy",
+                reason: format!("must be 1, 2, 4, 8 or 16, got {}", self.anisotropy),
+            });
+        }
+        if self.anisotropy > 1
+            && [self.mag_filter, self.min_filter, self.mipmap_filter]
+                .iter()
+                .any(|&f| f != wgpu::FilterMode::Linear)
+        {
+            return Err(TextureError::InvalidOption {
+                option: "anisotropy",
+                reason: "requires linear filtering".into(),
+            });
+        }
+        let (lod_min, lod_max) = self.lod_clamp;
+        if !(lod_min >= 0.0 && lod_min <= lod_max) {
+            return Err(TextureError::InvalidOption {
+                option: "lod_clamp",
+                reason: format!("must satisfy 0 <= min <= max, got {}..{}", lod_min, lod_max),
+            });
+        }
+        Ok(())
+    }
+
+    fn create_texture(
+        &self,
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        layers: u32,
+        format: wgpu::TextureFormat,
+        mip_level_count: u32,
+    ) -> Result<wgpu::Texture> {
+        check_extent(width, height, layers, &device.limits())?;
+        if self.sample_count > 1 {
+            if !self.usage.contains(wgpu::TextureUsages::RENDER_ATTACHMENT) {
+                return Err(TextureError::MissingUsage(
+                    wgpu::TextureUsages::RENDER_ATTACHMENT,
+                ));
+            }
+            if mip_level_count > 1 || layers > 1 {
+                return Err(TextureError::InvalidOption {
+                    option: "sample_count",
+                    reason: "multisampled textures cannot have mips or layers".into(),
+                });
+            }
+            // Without adapter specific format features only the guaranteed
+            // sample counts can be relied on.
+            let adapter_specific = device
+                .features()
+                .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+            let flags = format.describe().guaranteed_format_features.flags;
+            if !adapter_specific && !flags.sample_count_supported(self.sample_count) {
+                return Err(TextureError::InvalidOption {
+                    option: "sample_count",
+                    reason: format!(
+                        "{:?} does not support {}x multisampling",
+                        format, self.sample_count
+                    ),
+                });
+            }
+        }
+
+        Ok(device.create_texture(&wgpu::TextureDescriptor {
+            label: self.label,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: layers,
+            },
+            mip_level_count,
+            sample_count: self.sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | self.usage,
+            view_formats: &[],
+        }))
+    }
+
+    /// Creates the view, sampler and quad/uniform buffers around an already
+    /// populated texture.
+    fn finish(
+        &self,
+        device: &wgpu::Device,
+        texture: wgpu::Texture,
+        view_dimension: wgpu::TextureViewDimension,
+    ) -> Result<Texture> {
+        let size = texture.size();
+        self.finish_padded(device, texture, view_dimension, (size.width, size.height))
+    }
+
+    /// A copy of these settings that borrows nothing, for `Texture` to keep.
+    fn detached(&self) -> TextureBuilder<'static> {
+        let TextureBuilder {
+            label: _,
+            sampler: _,
+            size,
+            repetition,
+            address_mode_w,
+            srgb,
+            mipmaps,
+            mag_filter,
+            min_filter,
+            mipmap_filter,
+            anisotropy,
+            transform,
+            winding,
+            anchor,
+            flip_x,
+            flip_y,
+            half_texel_inset,
+            vertex_colors,
+            buffers,
+            premultiply_alpha,
+            usage,
+            native_format,
+            sample_count,
+            transform_slots,
+            color_transform,
+            color_adjust,
+            surface_size,
+            tile_grid,
+            max_dimension,
+            compare,
+            bitmap_transform,
+            pad_to_pot,
+            resize_filter,
+            fit,
+            lod_clamp,
+        } = *self;
+        TextureBuilder {
+            label: None,
+            sampler: None,
+            size,
+            repetition,
+            address_mode_w,
+            srgb,
+            mipmaps,
+            mag_filter,
+            min_filter,
+            mipmap_filter,
+            anisotropy,
+            transform,
+            winding,
+            anchor,
+            flip_x,
+            flip_y,
+            half_texel_inset,
+            vertex_colors,
+            buffers,
+            premultiply_alpha,
+            usage,
+            native_format,
+            sample_count,
+            transform_slots,
+            color_transform,
+            color_adjust,
+            surface_size,
+            tile_grid,
+            max_dimension,
+            compare,
+            bitmap_transform,
+            pad_to_pot,
+            resize_filter,
+            fit,
+            lod_clamp,
+        }
+    }
+
+    /// `finish` for a texture whose image only fills the top-left
+    /// `content_size` texels.
+    fn finish_padded(
+        &self,
+        device: &wgpu::Device,
+        texture: wgpu::Texture,
+        view_dimension: wgpu::TextureViewDimension,
+        content_size: (u32, u32),
+    ) -> Result<Texture> {
+        if self.anisotropy > 1 && texture.mip_level_count() == 1 {
+            return Err(TextureError::InvalidOption {
+                option: "anisotropy",
+                reason: "requires a texture with mip levels".into(),
+            });
+        }
+        let size = texture.size();
+        let quad = self.vertices(size, content_size);
+        let has_alpha = texture.format().describe().components == 4;
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: self.label,
+            dimension: Some(view_dimension),
+            ..Default::default()
+        });
+        let required = self.repetition.required_features();
+        if !device.features().contains(required) {
+            return Err(TextureError::MissingFeatures(required));
+        }
+        let format = texture.format();
+        if self.compare.is_some() && format.describe().sample_type != wgpu::TextureSampleType::Depth
+        {
+            return Err(TextureError::UnsupportedFormat(format));
+        }
+        let (address_mode_u, address_mode_v) = self.repetition.address_modes();
+        let sampler_desc = wgpu::SamplerDescriptor {
+            address_mode_u,
+            address_mode_v,
+            address_mode_w: self.address_mode_w,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            anisotropy_clamp: NonZeroU8::new(self.anisotropy).filter(|a| a.get() > 1),
+            border_color: self.repetition.border_color(),
+            compare: self.compare,
+            lod_min_clamp: self.lod_clamp.0,
+            lod_max_clamp: self.lod_clamp.1,
             ..Default::default()
+        };
+        let sampler = match self.sampler {
+            Some(sampler) => Arc::clone(sampler),
+            None => Arc::new(device.create_sampler(&wgpu::SamplerDescriptor {
+                label: sampler_label(self.label).as_deref(),
+                ..sampler_desc.clone()
+            })),
+        };
+
+        let transform_stride = (self.transform_slots > 0).then(|| {
+            let size = std::mem::size_of::<TextureTransform>() as wgpu::BufferAddress;
+            let align = device.limits().min_uniform_buffer_offset_alignment as u64;
+            size.div_ceil(align) * align
         });
+        let (vertex_buffer, index_buffer, uniform_buffer) = if self.buffers {
+            let (vertex_buffer, index_buffer, uniform_buffer) =
+                self.create_buffers(device, size, &quad, transform_stride)?;
+            (
+                Some(Arc::new(vertex_buffer)),
+                Some(Arc::new(index_buffer)),
+                Some(uniform_buffer),
+            )
+        } else {
+            (None, None, None)
+        };
+        let instance_buffer = match self.tile_grid {
+            Some((columns, rows)) if self.buffers => {
+                let (width, height) = self.quad_size(content_size);
+                let tiles: Vec<_> = (0..rows)
+                    .flat_map(|row| {
+                        (0..columns).map(move |column| TileInstance {
+                            offset: [column as f32 * width, row as f32 * height],
+                        })
+                    })
+                    .collect();
+                Some(Arc::new(device.create_buffer_init(
+                    &wgpu::util::BufferInitDescriptor {
+                        label: Some(
+                            &self.resource_label("instance buffer", "Tile instance buffer"),
+                        ),
+                        contents: bytemuck::cast_slice(&tiles),
+                        usage: wgpu::BufferUsages::VERTEX,
+                    },
+                )))
+            }
+            _ => None,
+        };
 
-        let x = 640.0;
-        let y = 360.0;
-
-        let image_width = dimensions.0 as f32;
-        let image_height = dimensions.1 as f32;
-
-        let vertices = [
-            Vertex {
-                position: [0.0, 0.0, 0.0],
-                tex_coords: [0.0, 0.0],
-            }, // A
-            Vertex {
-                position: [x, 0.0, 0.0],
-                // tex_coords: [image_width, 0.0],
-                tex_coords: [1.0, 0.0],
-            }, // B
-            Vertex {
-                position: [x, y, 0.0],
-                // tex_coords: [image_width, image_height],
-                tex_coords: [1.0, 1.0],
-            }, // C
-            Vertex {
-                position: [0.0, y, 0.0],
-                // tex_coords: [0.0, image_height],
-                tex_coords: [0.0, 1.0],
-            }, // D
-        ];
-        let indices: [u16; 6] = [1, 0, 2, 0, 2, 3];
+        let drop_log = DropLog::new(&texture);
+        Ok(Texture {
+            texture: Arc::new(texture),
+            view: Arc::new(view),
+            sampler,
+            vertex_buffer,
+            index_buffer,
+            instance_buffer,
+            uniform_buffer,
+            view_dimension,
+            sampler_desc,
+            transform_stride,
+            quad,
+            content_size,
+            has_alpha,
+            load_scale: 1.0,
+            builder: self.detached(),
+            label: self.label.map(str::to_owned),
+            drop_log,
+        })
+    }
+
+    /// "{label} {resource}" for the buffers made alongside the texture, so
+    /// they're recognisable in a GPU capture. `fallback` without a label.
+    fn resource_label(&self, resource: &str, fallback: &str) -> String {
+        resource_label(self.label, resource, fallback)
+    }
+
+    fn quad_size(&self, content_size: (u32, u32)) -> (f32, f32) {
+        self.size
+            .unwrap_or((content_size.0 as f32, content_size.1 as f32))
+    }
+
+    /// The quad's corners as they go into the vertex buffer.
+    fn vertices(&self, size: wgpu::Extent3d, content_size: (u32, u32)) -> [Vertex; 4] {
+        let (x, y) = self.quad_size(content_size);
+        let image_width = content_size.0 as f32;
+        let image_height = content_size.1 as f32;
+
+        let (mut vertices, _) = quad(x, y);
+        let [dx, dy] = self.anchor.offset(x, y);
+        let [fit_u, fit_v] = if x > 0.0 && y > 0.0 && image_width > 0.0 && image_height > 0.0 {
+            self.fit.uv_scale(image_width / image_height, x / y)
+        } else {
+            [1.0, 1.0]
+        };
+        for vertex in &mut vertices {
+            vertex.position[0] -= dx;
+            vertex.position[1] -= dy;
+            let [u, v] = vertex.tex_coords;
+            vertex.tex_coords = [0.5 + (u - 0.5) * fit_u, 0.5 + (v - 0.5) * fit_v];
+            if self.half_texel_inset {
+                let [u, v] = vertex.tex_coords;
+                vertex.tex_coords = [
+                    (0.5 + u * (image_width - 1.0)) / image_width,
+                    (0.5 + v * (image_height - 1.0)) / image_height,
+                ];
+            }
+            if self.flip_x {
+                vertex.tex_coords[0] = 1.0 - vertex.tex_coords[0];
+            }
+            if self.flip_y {
+                vertex.tex_coords[1] = 1.0 - vertex.tex_coords[1];
+            }
+            vertex.tex_coords[0] *= image_width / size.width as f32;
+            vertex.tex_coords[1] *= image_height / size.height as f32;
+        }
+        vertices
+    }
+
+    fn create_buffers(
+        &self,
+        device: &wgpu::Device,
+        size: wgpu::Extent3d,
+        vertices: &[Vertex; 4],
+        transform_stride: Option<wgpu::BufferAddress>,
+    ) -> Result<(wgpu::Buffer, wgpu::Buffer, wgpu::Buffer)> {
+        let image_width = size.width as f32;
+        let image_height = size.height as f32;
+
+        let indices = self.winding.indices();
 
+        let contents = match self.vertex_colors {
+            Some(colors) => {
+                let colored = vertices
+                    .iter()
+                    .zip(colors)
+                    .map(|(v, color)| VertexColored {
+                        position: v.position,
+                        tex_coords: v.tex_coords,
+                        color,
+                    })
+                    .collect::<Vec<_>>();
+                bytemuck::cast_slice(&colored).to_vec()
+            }
+            None => bytemuck::cast_slice(vertices).to_vec(),
+        };
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&vertices),
+            label: Some(&self.resource_label("vertex buffer", "Vertex Buffer")),
+            contents: &contents,
             usage: wgpu::BufferUsages::VERTEX,
         });
         let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
+            label: Some(&self.resource_label("index buffer", "Index Buffer")),
             contents: bytemuck::cast_slice(&indices),
             usage: wgpu::BufferUsages::INDEX,
         });
 
-        let bitmap_rotate = 0.9998476951563913;
-        let bitmap_scale = 0.017452406437283376;
-        // let bitmap_rotate = 0.7071067811865476;
-        // let bitmap_scale = 0.7071067811865476;
-        let m = [
-            [bitmap_scale, bitmap_rotate, 0.0, 0.0],
-            [-bitmap_rotate, bitmap_scale, 0.0, 0.0],
-            [0.0, 0.0, 1.0, 0.0],
-            [0.0, 0.0, 0.0, 1.0],
-        ];
+        let mut uniform = self
+            .transform
+            .unwrap_or_else(|| TextureTransform::identity(image_width, image_height));
+        if let Some(bitmap_transform) = self.bitmap_transform {
+            uniform.set_bitmap_transform(bitmap_transform)?;
+        }
+        if let Some(color_adjust) = self.color_adjust {
+            uniform.color_adjust = color_adjust;
+        }
+        if let Some(surface_size) = self.surface_size {
+            uniform.surface_size = surface_size;
+        }
 
+        // Every slot starts out with the same transform.
+        let mut contents = bytemuck::bytes_of(&uniform).to_vec();
+        if let Some(stride) = transform_stride {
+            contents.resize(stride as usize, 0);
+            contents = contents.repeat(self.transform_slots as usize);
+        }
         let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Texture transform uniform buffer"),
-            contents: bytemuck::cast_slice(&[TextureTransform {
-                transform: [
-                    [1.0, 0.0, 0.0, 0.0],
-                    [-0.0, 1.0, 0.0, 0.0],
-                    [0.0, 0.0, 1.0, 0.0],
-                    [0.0, 0.0, 0.0, 1.0],
-                ],
-                bitmap_transform: m,
-                inverse_bitmap_transform: inverse(m).unwrap(),
-                image_dimension: [image_width, image_height, 0.0, 1.0],
-            }]),
+            label: Some(&self.resource_label("uniform buffer", "Texture transform uniform buffer")),
+            contents: &contents,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        Ok(Self {
-            texture,
-            view,
-            sampler,
-            vertex_buffer,
-            index_buffer,
-            uniform_buffer,
-        })
+        Ok((vertex_buffer, index_buffer, uniform_buffer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diag(x: f32, y: f32, z: f32) -> [[f32; 4]; 4] {
+        [
+            [x, 0.0, 0.0, 0.0],
+            [0.0, y, 0.0, 0.0],
+            [0.0, 0.0, z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]
+    }
+
+    fn minors2(a: [[f32; 4]; 4]) -> [f32; 12] {
+        let [[a00, a01, a02, a03], [a10, a11, a12, a13], [a20, a21, a22, a23], [a30, a31, a32, a33]] =
+            a;
+        [
+            a00 * a11 - a01 * a10,
+            a00 * a12 - a02 * a10,
+            a00 * a13 - a03 * a10,
+            a01 * a12 - a02 * a11,
+            a01 * a13 - a03 * a11,
+            a02 * a13 - a03 * a12,
+            a20 * a31 - a21 * a30,
+            a20 * a32 - a22 * a30,
+            a20 * a33 - a23 * a30,
+            a21 * a32 - a22 * a31,
+            a21 * a33 - a23 * a31,
+            a22 * a33 - a23 * a32,
+        ]
+    }
+
+    fn determinant_from_minors(b: [f32; 12]) -> f32 {
+        b[0] * b[11] - b[1] * b[10] + b[2] * b[9] + b[3] * b[8] - b[4] * b[7] + b[5] * b[6]
+    }
+
+    /// The single precision cofactor expansion `inverse` used to be, to show
+    /// what working in f64 buys.
+    fn inverse_cofactor(a: [[f32; 4]; 4]) -> Option<[[f32; 4]; 4]> {
+        let mut out = [[0.0; 4]; 4];
+        let a00 = a[0][0];
+        let a01 = a[0][1];
+        let a02 = a[0][2];
+        let a03 = a[0][3];
+        let a10 = a[1][0];
+        let a11 = a[1][1];
+        let a12 = a[1][2];
+        let a13 = a[1][3];
+        let a20 = a[2][0];
+        let a21 = a[2][1];
+        let a22 = a[2][2];
+        let a23 = a[2][3];
+        let a30 = a[3][0];
+        let a31 = a[3][1];
+        let a32 = a[3][2];
+        let a33 = a[3][3];
+        let minors = minors2(a);
+        let [b00, b01, b02, b03, b04, b05, b06, b07, b08, b09, b10, b11] = minors;
+        let det = determinant_from_minors(minors);
+
+        if det == 0.0 {
+            return None;
+        }
+
+        let det = 1.0 / det;
+
+        out[0][0] = (a11 * b11 - a12 * b10 + a13 * b09) * det;
+        out[0][1] = (a02 * b10 - a01 * b11 - a03 * b09) * det;
+        out[0][2] = (a31 * b05 - a32 * b04 + a33 * b03) * det;
+        out[0][3] = (a22 * b04 - a21 * b05 - a23 * b03) * det;
+        out[1][0] = (a12 * b08 - a10 * b11 - a13 * b07) * det;
+        out[1][1] = (a00 * b11 - a02 * b08 + a03 * b07) * det;
+        out[1][2] = (a32 * b02 - a30 * b05 - a33 * b01) * det;
+        out[1][3] = (a20 * b05 - a22 * b02 + a23 * b01) * det;
+        out[2][0] = (a10 * b10 - a11 * b08 + a13 * b06) * det;
+        out[2][1] = (a01 * b08 - a00 * b10 - a03 * b06) * det;
+        out[2][2] = (a30 * b04 - a31 * b02 + a33 * b00) * det;
+        out[2][3] = (a21 * b02 - a20 * b04 - a23 * b00) * det;
+        out[3][0] = (a11 * b07 - a10 * b09 - a12 * b06) * det;
+        out[3][1] = (a00 * b09 - a01 * b07 + a02 * b06) * det;
+        out[3][2] = (a31 * b01 - a30 * b03 - a32 * b00) * det;
+        out[3][3] = (a20 * b03 - a21 * b01 + a22 * b00) * det;
+
+        Some(out)
+    }
+
+    #[test]
+    fn inverse_rejects_a_collapsed_axis() {
+        assert!(inverse(diag(1e-10, 1.0, 1.0)).is_none());
+        assert!(inverse(diag(1.0, 0.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn inverse_accepts_uniformly_small_scales() {
+        let inv = inverse(diag(0.01, 0.01, 0.01)).unwrap();
+        assert!(mat_approx_eq(inv, diag(100.0, 100.0, 100.0), 1e-4));
+        assert!(TextureTransform::from_trs([3.0, 4.0], 0.5, [1e-4, 1e-4], [64.0, 64.0]).is_ok());
+        let small_with_offset =
+            TextureTransform::from_trs([1e5, 1e5], 0.0, [1e-4, 1e-4], [1.0, 1.0]);
+        assert!(small_with_offset.is_ok());
+    }
+
+    /// max |m * inv - I|, accumulated in f64 so the check itself adds no
+    /// error.
+    fn inverse_residual(m: [[f32; 4]; 4], inv: [[f32; 4]; 4]) -> f64 {
+        inv.iter()
+            .enumerate()
+            .flat_map(|(col, inv_col)| {
+                (0..4).map(move |row| {
+                    let v: f64 = (0..4).map(|k| m[k][row] as f64 * inv_col[k] as f64).sum();
+                    let expected = if col == row { 1.0 } else { 0.0 };
+                    (v - expected).abs()
+                })
+            })
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn f64_inverse_survives_cancellation_that_breaks_f32() {
+        let m = [
+            [1.0, 1.0, 0.0, 0.0],
+            [1.0, 1.00001, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [300.0, 200.0, 0.0, 1.0],
+        ];
+        assert!(inverse_residual(m, inverse(m).unwrap()) < 1e-4);
+        assert!(inverse_residual(m, inverse_cofactor(m).unwrap()) > 1e-1);
+    }
+
+    #[test]
+    fn determinant_agrees_with_inverse() {
+        assert_eq!(determinant4(diag(2.0, 3.0, 4.0)), 24.0);
+        assert_eq!(determinant4(diag(-1.0, 1.0, 1.0)), -1.0);
+        assert_eq!(determinant4(diag(1.0, 0.0, 1.0)), 0.0);
+        assert_eq!(inverse(diag(1e-10, 1e-10, 1.0)), None);
+        assert_eq!(determinant4(diag(1e-10, 1e-10, 1.0)), 0.0);
+    }
+
+    #[test]
+    fn affine2d_inverse_undoes_the_transform() {
+        let t =
+            TextureTransform::from_affine2d([2.0, 0.0, 0.0, 4.0, 10.0, 20.0], [8.0, 8.0]).unwrap();
+        assert!(mat_approx_eq(
+            mul4(t.bitmap_transform, t.inverse_bitmap_transform),
+            IDENTITY,
+            1e-6
+        ));
+        assert!(
+            TextureTransform::from_affine2d([1.0, 2.0, 2.0, 4.0, 0.0, 0.0], [8.0, 8.0]).is_err()
+        );
+    }
+
+    #[test]
+    fn winding_sets_the_clip_space_orientation() {
+        let (vertices, _) = quad(64.0, 32.0);
+        for (winding, sign) in [(Winding::Ccw, 1.0), (Winding::Cw, -1.0)] {
+            for tri in winding.indices().chunks_exact(3) {
+                // The shader flips y on the way to clip space.
+                let [a, b, c] = [0, 1, 2].map(|i| {
+                    let [x, y, _] = vertices[tri[i] as usize].position;
+                    [x, -y]
+                });
+                let area = (b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1]);
+                assert_eq!(area.signum(), sign, "{:?} {:?}", winding, tri);
+            }
+        }
+    }
+
+    #[test]
+    fn premultiply_scales_colour_by_alpha() {
+        let builder = TextureBuilder::new().srgb(false).premultiply_alpha(true);
+        let pixels = builder.prepare_rgba8(&[200, 100, 50, 128, 10, 20, 30, 255]);
+        assert_eq!(&pixels[..], &[100, 50, 25, 128, 10, 20, 30, 255]);
+        let untouched = TextureBuilder::new().prepare_rgba8(&[200, 100, 50, 128]);
+        assert_eq!(&untouched[..], &[200, 100, 50, 128]);
+    }
+
+    /// For tests that need a device. They are `#[ignore]`d, run them with
+    /// `cargo test -- --ignored` where an adapter, even a software one, is
+    /// available.
+    fn gpu() -> (wgpu::Device, wgpu::Queue) {
+        pollster::block_on(crate::headless_device()).expect("no GPU adapter")
+    }
+
+    #[test]
+    fn empty_images_are_rejected() {
+        let limits = wgpu::Limits::default();
+        let err = check_extent(0, 0, 1, &limits).err().unwrap();
+        assert!(matches!(
+            err,
+            TextureError::Empty {
+                width: 0,
+                height: 0,
+                ..
+            }
+        ));
+        assert_eq!(err.to_string(), "image is 0x0, textures must not be empty");
+        assert!(check_extent(4, 0, 1, &limits).is_err());
+        assert!(check_extent(1, 1, 1, &limits).is_ok());
+    }
+
+    #[test]
+    fn oversized_textures_are_rejected() {
+        let limits = wgpu::Limits::downlevel_webgl2_defaults();
+        let max = limits.max_texture_dimension_2d;
+        assert!(matches!(
+            check_extent(max + 1, 1, 1, &limits),
+            Err(TextureError::TooLarge { requested, max: m }) if requested == max + 1 && m == max
+        ));
+        let layers = limits.max_texture_array_layers + 1;
+        assert!(matches!(
+            check_extent(1, 1, layers, &limits),
+            Err(TextureError::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn srgb_premultiply_works_in_linear_space() {
+        let builder = TextureBuilder::new().premultiply_alpha(true);
+        let pixels = builder.prepare_rgba8(&[255, 255, 0, 128]);
+        // Naive premultiplication of the encoded value would give 128.
+        assert_eq!(&pixels[..], &[188, 188, 0, 128]);
+    }
+
+    #[test]
+    fn flips_mirror_the_tex_coords() {
+        let size = wgpu::Extent3d {
+            width: 16,
+            height: 8,
+            depth_or_array_layers: 1,
+        };
+        let (plain, _) = quad(16.0, 8.0);
+        for flip_x in [false, true] {
+            for flip_y in [false, true] {
+                let vertices = TextureBuilder::new()
+                    .flip_x(flip_x)
+                    .flip_y(flip_y)
+                    .vertices(size, (16, 8));
+                for (vertex, plain) in vertices.iter().zip(&plain) {
+                    let [u, v] = plain.tex_coords;
+                    let expected = [
+                        if flip_x { 1.0 - u } else { u },
+                        if flip_y { 1.0 - v } else { v },
+                    ];
+                    assert_eq!(vertex.position, plain.position);
+                    assert_eq!(vertex.tex_coords, expected, "{} {}", flip_x, flip_y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tone_mapping_compresses_bright_pixels() {
+        let bright = image::Rgba32FImage::from_pixel(1, 1, image::Rgba([3.0, 0.5, 0.0, 1.0]));
+        let img = image::DynamicImage::ImageRgba32F(bright);
+        let pixel = |transform| tone_map_rgba8(&img, transform, false).get_pixel(0, 0).0;
+        assert_eq!(pixel(ColorTransform::None), [255, 128, 0, 255]);
+        // 3 / (1 + 3) and 0.5 / (1 + 0.5).
+        assert_eq!(pixel(ColorTransform::Reinhard), [191, 85, 0, 255]);
+        assert_eq!(pixel(ColorTransform::AcesApprox)[0], 243);
+    }
+
+    #[test]
+    fn sample_cpu_wraps_each_axis_per_repetition() {
+        const R: [u8; 4] = [255, 0, 0, 255];
+        const G: [u8; 4] = [0, 255, 0, 255];
+        const B: [u8; 4] = [0, 0, 255, 255];
+        const W: [u8; 4] = [255, 255, 255, 255];
+        let mut rgba = image::RgbaImage::new(2, 2);
+        for (x, y, colour) in [(0, 0, R), (1, 0, G), (0, 1, B), (1, 1, W)] {
+            rgba.put_pixel(x, y, image::Rgba(colour));
+        }
+        let img = image::DynamicImage::ImageRgba8(rgba);
+        let transform = TextureTransform::identity(2.0, 2.0);
+        let border = Repetition::ClampToBorder(wgpu::SamplerBorderColor::OpaqueBlack);
+        // The uvs land on texel centres one texel outside the image, so no
+        // filtering gets in the way.
+        let cases = [
+            ([1.25, 0.25], Repetition::Repeat, R),
+            ([1.25, 0.25], Repetition::RepeatX, R),
+            ([1.25, 0.25], Repetition::RepeatY, G),
+            ([1.25, 0.25], Repetition::NoRepeat, G),
+            ([1.25, 0.25], border, [0, 0, 0, 255]),
+            ([0.25, -0.25], Repetition::Repeat, B),
+            ([0.25, -0.25], Repetition::RepeatX, R),
+            ([0.25, -0.25], Repetition::RepeatY, B),
+            ([0.25, -0.25], Repetition::NoRepeat, R),
+            ([0.25, -0.25], border, [0, 0, 0, 255]),
+            ([-0.25, -0.25], Repetition::Repeat, W),
+            ([-0.25, -0.25], Repetition::RepeatX, G),
+            ([-0.25, -0.25], Repetition::RepeatY, B),
+            ([-0.25, -0.25], Repetition::NoRepeat, R),
+            ([-0.25, -0.25], border, [0, 0, 0, 255]),
+        ];
+        for (uv, repetition, expected) in cases {
+            let sampled = sample_cpu(&img, &transform, uv, repetition);
+            assert_eq!(sampled, expected, "{:?} at {:?}", repetition, uv);
+        }
+    }
+
+    #[test]
+    fn default_bitmap_transform_is_its_own_inverse() {
+        let transform = TextureTransform::default();
+        assert_eq!(transform.bitmap_transform, IDENTITY);
+        assert_eq!(
+            inverse(transform.bitmap_transform),
+            Some(transform.inverse_bitmap_transform)
+        );
+    }
+
+    #[test]
+    fn srgb_mips_average_in_linear_space() {
+        let mut checker = image::RgbaImage::new(2, 2);
+        for (x, y, value) in [(0, 0, 0), (1, 0, 255), (0, 1, 255), (1, 1, 0)] {
+            checker.put_pixel(x, y, image::Rgba([value, value, value, 255]));
+        }
+        let srgb = downsample(&checker, true);
+        assert_eq!(srgb.dimensions(), (1, 1));
+        // Half of white in linear light, encoded back to sRGB.
+        assert_eq!(srgb.get_pixel(0, 0).0, [188, 188, 188, 255]);
+        assert_eq!(
+            downsample(&checker, false).get_pixel(0, 0).0,
+            [128, 128, 128, 255]
+        );
+    }
+
+    #[test]
+    fn mul4_with_inverse_is_identity() {
+        let (sin, cos) = 0.7f32.sin_cos();
+        let m = [
+            [cos * 3.0, sin * 3.0, 0.0, 0.0],
+            [-sin * 0.5, cos * 0.5, 0.0, 0.0],
+            [0.0, 0.0, 2.0, 0.0],
+            [120.0, -40.0, 5.0, 1.0],
+        ];
+        let inv = inverse(m).unwrap();
+        assert!(mat_approx_eq(mul4(m, inv), IDENTITY, 1e-5));
+        assert!(mat_approx_eq(mul4(inv, m), IDENTITY, 1e-5));
+        // `b` applies first: scale, then translate.
+        let mut translate = IDENTITY;
+        translate[3] = [10.0, 20.0, 0.0, 1.0];
+        let composed = mul4(translate, diag(2.0, 2.0, 1.0));
+        assert_eq!(composed[3], [10.0, 20.0, 0.0, 1.0]);
+        assert_eq!(composed[0][0], 2.0);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn multisampled_textures_refuse_cpu_access() {
+        let (device, queue) = gpu();
+        let texture = TextureBuilder::new()
+            .sample_count(4)
+            .usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
+            .buffers(false)
+            .build_empty(&device, 4, 4, wgpu::TextureFormat::Rgba8UnormSrgb)
+            .unwrap();
+        let err = texture.update_rgba8(&queue, &[0; 64], 4, 4).err().unwrap();
+        assert!(matches!(err, TextureError::Multisampled));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn reload_at_a_new_size_keeps_the_builder_options() {
+        let (device, queue) = gpu();
+        let png = |width, height| {
+            let img = image::RgbaImage::from_pixel(width, height, image::Rgba([255, 0, 0, 128]));
+            let mut bytes = Vec::new();
+            image::DynamicImage::ImageRgba8(img)
+                .write_to(
+                    &mut std::io::Cursor::new(&mut bytes),
+                    image::ImageOutputFormat::Png,
+                )
+                .unwrap();
+            bytes
+        };
+        let img = image::load_from_memory(&png(3, 3)).unwrap();
+        let mut texture = TextureBuilder::new()
+            .pad_to_pot(true)
+            .premultiply_alpha(true)
+            .srgb(false)
+            .usage(wgpu::TextureUsages::COPY_SRC)
+            .build(&device, &queue, &img)
+            .unwrap();
+        assert!(texture.reload(&device, &queue, &png(5, 6)).unwrap());
+        assert_eq!(texture.dimensions(), (8, 8));
+        assert_eq!(texture.content_dimensions(), (5, 6));
+        assert_eq!(texture.quad[2].position, [5.0, 6.0, 0.0]);
+        assert!(texture.has_alpha());
+        let pixels = texture.read_rgba8(&device, &queue).unwrap();
+        assert_eq!(&pixels[..4], &[128, 0, 0, 128]);
+        // The same size again is uploaded into the padded texture in place.
+        assert!(!texture.reload(&device, &queue, &png(5, 6)).unwrap());
+        assert_eq!(texture.dimensions(), (8, 8));
+    }
+
+    #[test]
+    fn padding_keeps_rows_in_the_top_left() {
+        let padded = pad_rgba8(&[1, 2, 3, 4, 5, 6, 7, 8], 1, 2, 3);
+        assert_eq!(padded.len(), 2 * 3 * 4);
+        assert_eq!(&padded[..8], &[1, 2, 3, 4, 0, 0, 0, 0]);
+        assert_eq!(&padded[8..16], &[5, 6, 7, 8, 0, 0, 0, 0]);
+        assert!(padded[16..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn anisotropy_checks_the_built_mip_levels() {
+        let (device, queue) = gpu();
+        let levels: [(&[u8], u32, u32); 2] = [(&[0; 16], 2, 2), (&[0; 4], 1, 1)];
+        let builder = TextureBuilder::new().anisotropy(4);
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        assert!(builder
+            .build_mip_levels(&device, &queue, format, &levels)
+            .is_ok());
+        let single = builder
+            .mipmaps(true)
+            .build_mip_levels(&device, &queue, format, &levels[..1]);
+        assert!(matches!(
+            single,
+            Err(TextureError::InvalidOption {
+                option: "anisotropy",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn write_subimage_applies_the_builder_options() {
+        let (device, queue) = gpu();
+        let texture = TextureBuilder::new()
+            .premultiply_alpha(true)
+            .srgb(false)
+            .usage(wgpu::TextureUsages::COPY_SRC)
+            .build_rgba8(&device, &queue, &[0; 4 * 4 * 4], 4, 4)
+            .unwrap();
+        let sprite = image::RgbaImage::from_pixel(1, 1, image::Rgba([200, 100, 50, 128]));
+        texture
+            .write_subimage(&queue, &image::DynamicImage::ImageRgba8(sprite), (2, 1))
+            .unwrap();
+        let pixels = texture.read_rgba8(&device, &queue).unwrap();
+        let at = (4 + 2) * 4;
+        assert_eq!(&pixels[at..at + 4], &[100, 50, 25, 128]);
+    }
+
+    #[test]
+    fn invalid_options_name_the_option() {
+        let option = |builder: TextureBuilder| match builder.validate() {
+            Err(TextureError::InvalidOption { option, .. }) => option,
+            other => panic!("expected InvalidOption, got {:?}", other),
+        };
+        assert_eq!(
+            option(TextureBuilder::new().sample_count(3)),
+            "sample_count"
+        );
+        assert_eq!(option(TextureBuilder::new().anisotropy(3)), "anisotropy");
+        assert_eq!(
+            option(TextureBuilder::new().lod_clamp(2.0, 1.0)),
+            "lod_clamp"
+        );
+        assert!(TextureBuilder::new().validate().is_ok());
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn multisampled_textures_get_a_matching_layout() {
+        let (device, _) = gpu();
+        let texture = TextureBuilder::new()
+            .sample_count(4)
+            .usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
+            .build_empty(&device, 4, 4, wgpu::TextureFormat::Rgba8UnormSrgb)
+            .unwrap();
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let layout = texture.bind_group_layout_for(&device);
+        let _bind_group = texture.bind_group(&device, &layout);
+        let error = pollster::block_on(device.pop_error_scope());
+        assert!(error.is_none(), "{:?}", error);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn update_rgba8_takes_the_unpadded_size() {
+        let (device, queue) = gpu();
+        let texture = TextureBuilder::new()
+            .pad_to_pot(true)
+            .usage(wgpu::TextureUsages::COPY_SRC)
+            .build_rgba8(&device, &queue, &[0; 3 * 3 * 4], 3, 3)
+            .unwrap();
+        texture.update_rgba8(&queue, &[9; 3 * 3 * 4], 3, 3).unwrap();
+        assert!(matches!(
+            texture.update_rgba8(&queue, &[0; 4 * 4 * 4], 4, 4),
+            Err(TextureError::DimensionMismatch { .. })
+        ));
+        let pixels = texture.read_rgba8(&device, &queue).unwrap();
+        assert_eq!(&pixels[8..16], &[9, 9, 9, 9, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn resource_labels_derive_from_the_texture_label() {
+        assert_eq!(
+            resource_label(Some("atlas"), "staging buffer", "Texture staging buffer"),
+            "atlas staging buffer"
+        );
+        assert_eq!(
+            resource_label(None, "staging buffer", "Texture staging buffer"),
+            "Texture staging buffer"
+        );
+        assert_eq!(
+            sampler_label(Some("atlas")).as_deref(),
+            Some("atlas sampler")
+        );
+        assert_eq!(sampler_label(None), None);
     }
 }